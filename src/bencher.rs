@@ -1,172 +1,64 @@
-use crate::{bfunc::Bfunc, graph::COLOR_WHEEL};
-use itertools::Itertools;
-use plotters::prelude::*;
+use crate::{
+    bfunc::{Bfunc, Sampling, Warmup},
+    corpus::Input,
+    report::{BenchReport, CodecReport, Environment, Failure},
+};
 
 pub struct Bencher<'input, F1, F2>
 where
-    F1: Fn(&[u8]) -> Vec<u8>,
-    F2: Fn(&[u8]),
+    F1: Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
+    F2: Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
 {
     functions: Vec<Bfunc<F1, F2>>,
-    inputs: Vec<&'input [u8]>,
+    inputs: &'input [Input],
 }
 
 impl<'input, F1, F2> Bencher<'input, F1, F2>
 where
-    F1: Fn(&[u8]) -> Vec<u8>,
-    F2: Fn(&[u8]),
+    F1: Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
+    F2: Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
 {
-    pub fn new(functions: Vec<Bfunc<F1, F2>>, inputs: Vec<&'input [u8]>) -> Self {
+    pub fn new(functions: Vec<Bfunc<F1, F2>>, inputs: &'input [Input]) -> Self {
         Self { functions, inputs }
     }
 
-    pub fn run(self, num_of_samples: u8) {
-        let cpu_name = sysinfo::System::new_with_specifics(
-            sysinfo::RefreshKind::new().with_cpu(sysinfo::CpuRefreshKind::new()),
-        )
-        .cpus()
-        .first()
-        .unwrap()
-        .brand()
-        .replace(' ', "_");
-
-        let mut title = self
-            .functions
-            .iter()
-            .fold(cpu_name, |acc, x| acc + "_" + x.title);
-        title.push_str(".png");
-
-        let root = BitMapBackend::new(&title, (1200, 2100)).into_drawing_area();
-        root.fill(&WHITE).unwrap();
-
-        let (top, middle_bottom) = root.split_vertically(600);
-        let (middle, bottom) = middle_bottom.split_vertically(600);
-
-        let mut decomp_size_comp_size_chart = ChartBuilder::on(&top)
-            .caption("DS-CS", ("sans-serif", 25).into_font())
-            .margin(10)
-            .x_label_area_size(40)
-            .y_label_area_size(50)
-            .build_cartesian_2d(0f64..215f64, 0f64..32f64)
-            .unwrap();
-
-        decomp_size_comp_size_chart
-            .configure_mesh()
-            .x_desc("decompressed size [MB]")
-            .y_desc("compressed size [MB]")
-            .axis_desc_style(("sans-serif", 20).into_font())
-            .draw()
-            .unwrap();
-
-        let mut decomp_size_comp_time_chart = ChartBuilder::on(&middle)
-            .caption("DS-CT", ("sans-serif", 25).into_font())
-            .margin(10)
-            .x_label_area_size(40)
-            .y_label_area_size(50)
-            .build_cartesian_2d(0f64..215f64, 0f64..6f64)
-            .unwrap();
-
-        decomp_size_comp_time_chart
-            .configure_mesh()
-            .x_desc("decompressed size [MB]")
-            .y_desc("compression time [s]")
-            .axis_desc_style(("sans-serif", 20).into_font())
-            .draw()
-            .unwrap();
-
-        let mut comp_size_decomp_time_chart = ChartBuilder::on(&bottom)
-            .caption("CS-DT", ("sans-serif", 25).into_font())
-            .margin(10)
-            .x_label_area_size(40)
-            .y_label_area_size(50)
-            .build_cartesian_2d(0f64..32f64, 0f64..0.5f64)
-            .unwrap();
-
-        comp_size_decomp_time_chart
-            .configure_mesh()
-            .x_desc("compressed size [MB]")
-            .y_desc("decompression time [s]")
-            .axis_desc_style(("sans-serif", 20).into_font())
-            .draw()
-            .unwrap();
-
-        for (idx, bfunc) in self.functions.into_iter().enumerate() {
-            let color = COLOR_WHEEL.get(idx).unwrap_or_else(|| {
-                eprintln!("Not enough colors in COLOR_WHEEL");
-                &RED
+    /// Benchmarks every function on every input, failures are recorded in the report instead of stopping the run
+    pub fn measure(
+        self,
+        sampling: Sampling,
+        warmup: Warmup,
+        warmup_once_per_codec: bool,
+    ) -> BenchReport {
+        let environment = Environment::current();
+        let mut codecs: Vec<CodecReport> = Vec::new();
+        let mut failures: Vec<Failure> = Vec::new();
+        for bfunc in self.functions.into_iter() {
+            let mut results = Vec::new();
+            for (input_idx, input) in self.inputs.iter().enumerate() {
+                // when warming up once per codec, only the first input is preceded by a warm-up
+                let warmup = (!warmup_once_per_codec || input_idx == 0).then_some(warmup);
+                match bfunc.bench(input, sampling, warmup) {
+                    Ok(result) => results.push(result),
+                    Err(error) => {
+                        eprintln!("'{}' failed on '{}': {:#}", bfunc.title, input.name, error);
+                        failures.push(Failure {
+                            title: bfunc.title.clone(),
+                            input_name: input.name.clone(),
+                            error: format!("{:#}", error),
+                        });
+                    }
+                }
+            }
+            codecs.push(CodecReport {
+                title: bfunc.title,
+                results,
             });
-            let (ds_cs_res, ds_ct_res, cs_dt_res): (
-                Vec<(f64, f64)>,
-                Vec<(f64, f64)>,
-                Vec<(f64, f64)>,
-            ) = self
-                .inputs
-                .iter()
-                .map(|input| {
-                    let result = bfunc.bench(input, num_of_samples);
-                    (
-                        result.decomp_size_comp_size,
-                        result.decomp_size_comp_time,
-                        result.comp_size_decomp_time,
-                    )
-                })
-                .multiunzip();
-
-            decomp_size_comp_size_chart
-                .draw_series(
-                    crate::graph::interpolation::linear(ds_cs_res)
-                        .into_iter()
-                        .map(|coord| Circle::new(coord, 1, color.stroke_width(1))),
-                )
-                .unwrap()
-                .label(bfunc.title)
-                .legend(move |(x, y)| Circle::new((x + 10, y), 7, color.stroke_width(2)));
-
-            decomp_size_comp_time_chart
-                .draw_series(
-                    crate::graph::interpolation::linear(ds_ct_res)
-                        .into_iter()
-                        .map(|coord| Circle::new(coord, 1, color.stroke_width(1))),
-                )
-                .unwrap()
-                .label(bfunc.title)
-                .legend(move |(x, y)| Circle::new((x + 10, y), 7, color.stroke_width(2)));
-
-            comp_size_decomp_time_chart
-                .draw_series(
-                    crate::graph::interpolation::linear(cs_dt_res.clone())
-                        .into_iter()
-                        .map(|coord| Circle::new(coord, 1, color.stroke_width(1))),
-                )
-                .unwrap()
-                .label(bfunc.title)
-                .legend(move |(x, y)| Circle::new((x + 10, y), 7, color.stroke_width(2)));
         }
 
-        decomp_size_comp_size_chart
-            .configure_series_labels()
-            .position(SeriesLabelPosition::UpperLeft)
-            .label_font(("sans-serif", 20).into_font())
-            .border_style(BLACK)
-            .draw()
-            .unwrap();
-
-        decomp_size_comp_time_chart
-            .configure_series_labels()
-            .position(SeriesLabelPosition::UpperLeft)
-            .label_font(("sans-serif", 20).into_font())
-            .border_style(BLACK)
-            .draw()
-            .unwrap();
-
-        comp_size_decomp_time_chart
-            .configure_series_labels()
-            .position(SeriesLabelPosition::UpperLeft)
-            .label_font(("sans-serif", 20).into_font())
-            .border_style(BLACK)
-            .draw()
-            .unwrap();
-
-        root.present().unwrap();
+        BenchReport {
+            environment,
+            codecs,
+            failures,
+        }
     }
 }
@@ -1,84 +1,412 @@
+use crate::{
+    corpus::Input,
+    cpu,
+    memory::{self, MemUsage},
+    stats::Summary,
+};
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
 // bench function
 pub struct Bfunc<F1, F2>
 where
-    F1: Fn(&[u8]) -> Vec<u8>,
-    F2: Fn(&[u8]),
+    F1: Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
+    F2: Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
 {
-    pub title: &'static str,
+    pub title: String,
     pub compressor: F1,
     pub decompressor: F2,
+    /// CRC32 of the decompressed inputs the codec was trained on (e.g. its dictionary),
+    /// measuring them would flatter the codec
+    pub training_set: Vec<u32>,
+    /// the codec allocates its buffers in C, out of sight of the counting allocator
+    pub allocates_in_c: bool,
 }
 
 impl<F1, F2> Bfunc<F1, F2>
 where
-    F1: Fn(&[u8]) -> Vec<u8>,
-    F2: Fn(&[u8]),
+    F1: Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
+    F2: Fn(&[u8]) -> anyhow::Result<Vec<u8>>,
 {
-    pub fn new(title: &'static str, compressor: F1, decompressor: F2) -> Self {
+    pub fn new(title: String, compressor: F1, decompressor: F2) -> Self {
         Self {
             title,
             compressor,
             decompressor,
+            training_set: Vec::new(),
+            allocates_in_c: false,
         }
     }
-    pub fn bench(&self, uncompressed_input_data: &[u8], num_of_samples: u8) -> BenchResult {
-        println!("--  Benchmarking '{}'  --", self.title);
 
-        println!("## warming up...");
-        let _start = Instant::now();
-        let compressed_input_data = (self.compressor)(uncompressed_input_data);
-        while _start.elapsed().as_secs() < 3 {
-            (self.compressor)(uncompressed_input_data);
+    pub fn trained_on(mut self, training_set: Vec<u32>) -> Self {
+        self.training_set = training_set;
+        self
+    }
+
+    pub fn allocates_in_c(mut self, allocates_in_c: bool) -> Self {
+        self.allocates_in_c = allocates_in_c;
+        self
+    }
+
+    /// `warmup` is skipped when `None`, e.g. when the codec was already warmed up on another input
+    pub fn bench(
+        &self,
+        input: &Input,
+        sampling: Sampling,
+        warmup: Option<Warmup>,
+    ) -> anyhow::Result<BenchResult> {
+        println!("--  Benchmarking '{}' on '{}'  --", self.title, input.name);
+        let uncompressed_input_data = input.data.as_slice();
+        let compressed_input_data = (self.compressor)(uncompressed_input_data)?;
+
+        println!("## verifying...");
+        let input_hash = self.verify_round_trip(input, &compressed_input_data)?;
+
+        if let Some(warmup) = warmup {
+            println!("## warming up compression...");
+            warmup.run(|| (self.compressor)(uncompressed_input_data).map(|_| ()))?;
         }
 
+        // the compression series may use up to half of the budget, the decompression series gets what is left
         println!("## compressing...");
-        let mut durations: Vec<Duration> = Vec::new();
-        for _ in 0..num_of_samples {
-            let inst = Instant::now();
-            let _ = (self.compressor)(uncompressed_input_data);
-            durations.push(inst.elapsed())
+        let comp_start = Instant::now();
+        let (durations, cpu_durations) = sampling.sample(sampling.budget() / 2, || {
+            (self.compressor)(uncompressed_input_data).map(|_| ())
+        })?;
+        let comp_spent = comp_start.elapsed();
+        let comp_time = Summary::new(&durations);
+        let comp_cpu_time = (!cpu_durations.is_empty()).then(|| Summary::new(&cpu_durations));
+        println!(
+            "median compression time: {:.8} (95% CI {:.8}..{:.8}, {} samples)",
+            comp_time.median, comp_time.ci_low, comp_time.ci_high, comp_time.num_of_samples
+        );
+        if let Some(cpu_time) = comp_cpu_time.as_ref() {
+            println!(
+                "median compression CPU time: {:.8} ({:.2} cores busy on average)",
+                cpu_time.median,
+                cpu_time.mean / comp_time.mean
+            );
+        }
+
+        if let Some(warmup) = warmup {
+            println!("## warming up decompression...");
+            warmup.run(|| (self.decompressor)(&compressed_input_data).map(|_| ()))?;
         }
-        let median_comp_time = Self::median(durations).as_secs_f64();
-        println!("median compression time: {:.8}", median_comp_time);
 
         println!("## decompressing...");
-        let mut durations: Vec<Duration> = Vec::new();
-        for _ in 0..num_of_samples {
-            let inst = Instant::now();
-            (self.decompressor)(&compressed_input_data);
-            durations.push(inst.elapsed())
+        let (durations, cpu_durations) = sampling
+            .sample(sampling.budget().saturating_sub(comp_spent), || {
+                (self.decompressor)(&compressed_input_data).map(|_| ())
+            })?;
+        let decomp_time = Summary::new(&durations);
+        let decomp_cpu_time = (!cpu_durations.is_empty()).then(|| Summary::new(&cpu_durations));
+        println!(
+            "median decompression time: {:.8} (95% CI {:.8}..{:.8}, {} samples)",
+            decomp_time.median, decomp_time.ci_low, decomp_time.ci_high, decomp_time.num_of_samples
+        );
+        if let Some(cpu_time) = decomp_cpu_time.as_ref() {
+            println!(
+                "median decompression CPU time: {:.8} ({:.2} cores busy on average)",
+                cpu_time.median,
+                cpu_time.mean / decomp_time.mean
+            );
+        }
+
+        // measured on separate runs, the allocations are only counted while `memory::measure` runs
+        // so that the bookkeeping does not weigh on the timings
+        println!("## measuring memory usage...");
+        let (compressed, comp_memory) = memory::measure(
+            || (self.compressor)(uncompressed_input_data),
+            self.allocates_in_c,
+        );
+        drop(compressed?);
+        let (decompressed, decomp_memory) = memory::measure(
+            || (self.decompressor)(&compressed_input_data),
+            self.allocates_in_c,
+        );
+        drop(decompressed?);
+        let format_peak = |peak: Option<usize>| {
+            peak.map_or("not measured".to_string(), |peak| {
+                format!("{:.3} MB", peak as f64 / 1e6)
+            })
+        };
+        println!(
+            "peak memory usage: {} compressing, {} decompressing",
+            format_peak(comp_memory.peak),
+            format_peak(decomp_memory.peak)
+        );
+
+        Ok(BenchResult {
+            title: self.title.clone(),
+            input_name: input.name.clone(),
+            input_hash,
+            uncompressed_size: uncompressed_input_data.len(),
+            compressed_size: compressed_input_data.len(),
+            comp_time,
+            decomp_time,
+            comp_cpu_time,
+            decomp_cpu_time,
+            comp_memory,
+            decomp_memory,
+        })
+    }
+
+    /// Checks that decompressing gives back the exact input, errors otherwise as a codec that corrupts notes must not be benchmarked,
+    /// returns the CRC32 of the input
+    fn verify_round_trip(
+        &self,
+        input: &Input,
+        compressed_input_data: &[u8],
+    ) -> anyhow::Result<u32> {
+        let input_hash = crc32fast::hash(&input.data);
+        let round_trip = (self.decompressor)(compressed_input_data)?;
+        let round_trip_hash = crc32fast::hash(&round_trip);
+
+        if round_trip != input.data {
+            let first_mismatch = input
+                .data
+                .iter()
+                .zip(round_trip.iter())
+                .position(|(a, b)| a != b)
+                .unwrap_or(input.data.len().min(round_trip.len()));
+            anyhow::bail!(
+                "'{}' does not round-trip '{}': expected {} bytes (crc32 {:08x}), got {} bytes (crc32 {:08x}), first mismatch at byte {}",
+                self.title,
+                input.name,
+                input.data.len(),
+                input_hash,
+                round_trip.len(),
+                round_trip_hash,
+                first_mismatch,
+            );
         }
-        let median_decomp_time = Self::median(durations).as_secs_f64();
-        println!("median decompression time: {:.8}", median_decomp_time);
+        Ok(input_hash)
+    }
+}
 
-        let decomp_size_mb = uncompressed_input_data.len() as f64 / 1e6;
-        let comp_size_mb = compressed_input_data.len() as f64 / 1e6;
+/// How many samples are taken for each timing series
+#[derive(Clone, Copy)]
+pub enum Sampling {
+    Fixed(u8),
+    /// Keeps sampling until the confidence interval of the median, relative to the median, is narrower than `target`
+    /// or until `budget` is spent on the (codec, input) pair, while taking between `min_samples` and `max_samples`
+    Adaptive {
+        target: f64,
+        budget: Duration,
+        min_samples: u8,
+        max_samples: u8,
+    },
+}
 
-        BenchResult {
-            decomp_size_comp_size: (decomp_size_mb, comp_size_mb),
-            decomp_size_comp_time: (decomp_size_mb, median_comp_time),
-            comp_size_decomp_time: (comp_size_mb, median_decomp_time),
+impl Sampling {
+    fn budget(&self) -> Duration {
+        match *self {
+            Self::Fixed(_) => Duration::MAX,
+            Self::Adaptive { budget, .. } => budget,
         }
     }
 
-    fn median(mut input: Vec<Duration>) -> Duration {
-        input.sort();
-        let n = input.len();
-        // if n is odd
-        if input.len() % 2 == 1 {
-            input.remove((n + 1) / 2)
+    /// Returns the wall-clock and the CPU time of every sample, the latter being empty when unsupported,
+    /// adaptive sampling stops once `budget` is spent
+    fn sample(
+        &self,
+        budget: Duration,
+        mut f: impl FnMut() -> anyhow::Result<()>,
+    ) -> anyhow::Result<(Vec<Duration>, Vec<Duration>)> {
+        let deadline = Instant::now().checked_add(budget);
+        let mut durations: Vec<Duration> = Vec::new();
+        let mut cpu_durations: Vec<Duration> = Vec::new();
+        let mut time = || -> anyhow::Result<Duration> {
+            let cpu_start = cpu::process_time();
+            let inst = Instant::now();
+            f()?;
+            let elapsed = inst.elapsed();
+            if let (Some(cpu_start), Some(cpu_end)) = (cpu_start, cpu::process_time()) {
+                cpu_durations.push(cpu_end.saturating_sub(cpu_start));
+            }
+            Ok(elapsed)
+        };
+
+        match *self {
+            Self::Fixed(num_of_samples) => {
+                for _ in 0..num_of_samples {
+                    durations.push(time()?);
+                }
+            }
+            Self::Adaptive {
+                target,
+                min_samples,
+                max_samples,
+                ..
+            } => {
+                // the bootstrap is not free, so the confidence interval is only checked every ~10% more samples
+                let mut next_check = usize::from(min_samples);
+                while durations.len() < usize::from(max_samples) {
+                    durations.push(time()?);
+                    if durations.len() < usize::from(min_samples) {
+                        continue;
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        break;
+                    }
+                    if durations.len() >= next_check {
+                        if Summary::new(&durations).relative_ci() <= target {
+                            break;
+                        }
+                        next_check = durations.len() + (durations.len() / 10).max(1);
+                    }
+                }
+            }
         }
-        // if n is even
-        else {
-            (input.remove(n / 2) + input.remove(n / 2)) / 2
+        Ok((durations, cpu_durations))
+    }
+}
+
+/// How a codec is warmed up before a timing series
+#[derive(Clone, Copy)]
+pub enum Warmup {
+    Iterations(u32),
+    Duration(Duration),
+    /// Runs until the spread of the last `window` timings, relative to their median, is below `tolerance`,
+    /// or until `max` is spent
+    Stable {
+        tolerance: f64,
+        window: usize,
+        max: Duration,
+    },
+}
+
+impl Warmup {
+    fn run(&self, mut f: impl FnMut() -> anyhow::Result<()>) -> anyhow::Result<()> {
+        let start = Instant::now();
+        match *self {
+            Self::Iterations(iterations) => {
+                for _ in 0..iterations {
+                    f()?;
+                }
+            }
+            Self::Duration(duration) => {
+                while start.elapsed() < duration {
+                    f()?;
+                }
+            }
+            Self::Stable {
+                tolerance,
+                window,
+                max,
+            } => {
+                let mut timings: Vec<f64> = Vec::new();
+                while start.elapsed() < max {
+                    let inst = Instant::now();
+                    f()?;
+                    timings.push(inst.elapsed().as_secs_f64());
+
+                    if timings.len() >= window {
+                        let mut last = timings[timings.len() - window..].to_vec();
+                        last.sort_by(f64::total_cmp);
+                        let spread = (last[window - 1] - last[0]) / crate::stats::median(&last);
+                        if spread <= tolerance {
+                            break;
+                        }
+                    }
+                }
+            }
         }
+        Ok(())
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct BenchResult {
-    pub decomp_size_comp_size: (f64, f64),
-    pub decomp_size_comp_time: (f64, f64),
-    pub comp_size_decomp_time: (f64, f64),
+    pub title: String,
+    pub input_name: String,
+    /// CRC32 of the uncompressed input
+    pub input_hash: u32,
+    pub uncompressed_size: usize,
+    pub compressed_size: usize,
+    pub comp_time: Summary,
+    pub decomp_time: Summary,
+    /// user + system CPU time of the whole process, `None` when unsupported
+    pub comp_cpu_time: Option<Summary>,
+    pub decomp_cpu_time: Option<Summary>,
+    pub comp_memory: MemUsage,
+    pub decomp_memory: MemUsage,
+}
+
+impl BenchResult {
+    pub fn ratio(&self) -> f64 {
+        self.uncompressed_size as f64 / self.compressed_size as f64
+    }
+
+    /// uncompressed megabytes processed per second
+    pub fn comp_throughput(&self) -> f64 {
+        self.uncompressed_size as f64 / 1e6 / self.comp_time.median
+    }
+
+    /// uncompressed megabytes produced per second
+    pub fn decomp_throughput(&self) -> f64 {
+        self.uncompressed_size as f64 / 1e6 / self.decomp_time.median
+    }
+
+    /// average number of cores busy while compressing, CPU time over wall-clock time
+    pub fn comp_parallelism(&self) -> Option<f64> {
+        self.comp_cpu_time
+            .as_ref()
+            .map(|cpu_time| cpu_time.mean / self.comp_time.mean)
+    }
+
+    /// average number of cores busy while decompressing, CPU time over wall-clock time
+    pub fn decomp_parallelism(&self) -> Option<f64> {
+        self.decomp_cpu_time
+            .as_ref()
+            .map(|cpu_time| cpu_time.mean / self.decomp_time.mean)
+    }
+
+    pub fn decomp_size_comp_cpu_time(&self) -> Option<(f64, f64)> {
+        self.comp_cpu_time
+            .as_ref()
+            .map(|cpu_time| (self.uncompressed_size as f64 / 1e6, cpu_time.median))
+    }
+
+    pub fn decomp_size_comp_size(&self) -> (f64, f64) {
+        (
+            self.uncompressed_size as f64 / 1e6,
+            self.compressed_size as f64 / 1e6,
+        )
+    }
+
+    pub fn decomp_size_comp_time(&self) -> (f64, f64) {
+        (self.uncompressed_size as f64 / 1e6, self.comp_time.median)
+    }
+
+    pub fn comp_size_decomp_time(&self) -> (f64, f64) {
+        (self.compressed_size as f64 / 1e6, self.decomp_time.median)
+    }
+
+    pub fn decomp_size_ratio(&self) -> (f64, f64) {
+        (self.uncompressed_size as f64 / 1e6, self.ratio())
+    }
+
+    pub fn decomp_size_comp_throughput(&self) -> (f64, f64) {
+        (self.uncompressed_size as f64 / 1e6, self.comp_throughput())
+    }
+
+    pub fn decomp_size_decomp_throughput(&self) -> (f64, f64) {
+        (
+            self.uncompressed_size as f64 / 1e6,
+            self.decomp_throughput(),
+        )
+    }
+
+    pub fn decomp_size_comp_memory(&self) -> Option<(f64, f64)> {
+        self.comp_memory
+            .peak
+            .map(|peak| (self.uncompressed_size as f64 / 1e6, peak as f64 / 1e6))
+    }
+
+    pub fn decomp_size_decomp_memory(&self) -> Option<(f64, f64)> {
+        self.decomp_memory
+            .peak
+            .map(|peak| (self.uncompressed_size as f64 / 1e6, peak as f64 / 1e6))
+    }
 }
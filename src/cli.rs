@@ -0,0 +1,322 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    bfunc::{Sampling, Warmup},
+    diff::Thresholds,
+    graph::{Palette, Scales},
+    render::ChartFormat,
+    zstd_dict::{Algorithm, Params},
+};
+
+#[derive(Parser)]
+#[command(about = "Compression benchmarks on rnote files")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Benchmark codecs on the corpus and draw the charts
+    Bench(BenchArgs),
+    /// Draw the charts of a saved report again
+    Render(RenderArgs),
+    /// Draw the results of several saved reports (e.g. from different machines) on the same charts
+    Compare(CompareArgs),
+    /// Compare a new report to a baseline, fails when a codec regressed
+    Diff(DiffArgs),
+    /// Train a zstd dictionary
+    TrainDict(TrainDictArgs),
+    /// Train zstd dictionaries of several sizes and measure their gains on held-out notes
+    DictSweep(DictSweepArgs),
+    /// Print information about the corpus
+    Inspect(InspectArgs),
+}
+
+#[derive(Args)]
+pub struct CorpusArgs {
+    /// Directories or glob patterns of .rnote files
+    #[arg(long = "corpus", default_value = "./files")]
+    pub sources: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Codec specs, e.g. `zstd:9:mt=auto`, `brotli:4:buf=4096:win=24`, `gzip:5`, `par-gzip:9`, `zstd-dict:9:dict=rnote.dict`, `zstd-opt`, `lz4-hc:9`, `snappy`, `xz:6:extreme=true`, `bzip2:9`
+    #[arg(short, long = "codec", default_values = DEFAULT_CODECS)]
+    pub codecs: Vec<String>,
+    /// Number of samples per input
+    #[arg(short = 'n', long, default_value_t = 16, value_parser = clap::value_parser!(u8).range(1..))]
+    pub samples: u8,
+    /// Sample until the median is precise enough instead of taking a fixed number of samples
+    #[arg(long, conflicts_with = "samples")]
+    pub adaptive: bool,
+    /// Adaptive sampling: target width of the 95% confidence interval of the median, relative to the median
+    #[arg(long, default_value_t = 0.02, requires = "adaptive")]
+    pub target_ci: f64,
+    /// Adaptive sampling: time budget per codec and input, in seconds
+    #[arg(long, default_value_t = 10.0, requires = "adaptive")]
+    pub budget: f64,
+    /// Adaptive sampling: minimum number of samples
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u8).range(1..), requires = "adaptive")]
+    pub min_samples: u8,
+    /// Adaptive sampling: maximum number of samples
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(1..), requires = "adaptive")]
+    pub max_samples: u8,
+    /// Warm-up before every timing series: a duration in seconds (`3`), a number of iterations (`10x`),
+    /// or `stable[:<tolerance>[:<max seconds>]]` to run until the timings stop changing
+    #[arg(long, default_value = "3", value_parser = parse_warmup)]
+    pub warmup: Warmup,
+    /// Warm up each codec only once, on the first input, instead of before every input
+    #[arg(long)]
+    pub warmup_once: bool,
+    /// Directory the charts and the report are written to
+    #[arg(short, long, default_value = ".")]
+    pub output_dir: PathBuf,
+    #[command(flatten)]
+    pub chart: ChartArgs,
+    #[command(flatten)]
+    pub corpus: CorpusArgs,
+}
+
+/// The general benchmark, with gzip-5 being what rnote currently uses
+const DEFAULT_CODECS: [&str; 6] = [
+    "gzip:5:name=gzip-5-(current)",
+    "brotli:2:buf=4096:win=24",
+    "brotli:4:buf=4096:win=24",
+    "par-gzip:9",
+    "zstd:9:mt=auto",
+    "zstd-opt",
+];
+
+impl BenchArgs {
+    pub fn sampling(&self) -> anyhow::Result<Sampling> {
+        if !self.adaptive {
+            return Ok(Sampling::Fixed(self.samples));
+        }
+        if self.min_samples > self.max_samples {
+            anyhow::bail!("--min-samples must not be greater than --max-samples");
+        }
+        Ok(Sampling::Adaptive {
+            target: self.target_ci,
+            budget: Duration::try_from_secs_f64(self.budget)?,
+            min_samples: self.min_samples,
+            max_samples: self.max_samples,
+        })
+    }
+}
+
+fn parse_warmup(warmup: &str) -> Result<Warmup, String> {
+    let parse_secs = |secs: &str| {
+        secs.parse()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or(format!("invalid duration '{}'", secs))
+    };
+
+    if let Some(iterations) = warmup.strip_suffix('x') {
+        return iterations
+            .parse()
+            .map(Warmup::Iterations)
+            .map_err(|_| format!("invalid number of iterations '{}'", iterations));
+    }
+    if warmup == "stable" || warmup.starts_with("stable:") {
+        let mut params = warmup.split(':').skip(1);
+        let tolerance = match params.next() {
+            Some(tolerance) => tolerance
+                .parse()
+                .map_err(|_| format!("invalid tolerance '{}'", tolerance))?,
+            None => 0.05,
+        };
+        let max = match params.next() {
+            Some(max) => parse_secs(max)?,
+            None => Duration::from_secs(10),
+        };
+        return Ok(Warmup::Stable {
+            tolerance,
+            window: 5,
+            max,
+        });
+    }
+    parse_secs(warmup).map(Warmup::Duration)
+}
+
+#[derive(Args)]
+pub struct ChartArgs {
+    /// Image formats of the charts
+    #[arg(long = "format", value_delimiter = ',', default_value = "png")]
+    pub formats: Vec<ChartFormat>,
+    /// Colours of the series, codecs past the last colour also get markers and dash patterns
+    #[arg(long, value_enum, default_value_t)]
+    pub palette: Palette,
+    /// Draw sizes (and memory usage) on a log scale
+    #[arg(long)]
+    pub log_size: bool,
+    /// Draw times (and throughputs) on a log scale
+    #[arg(long)]
+    pub log_time: bool,
+}
+
+impl ChartArgs {
+    pub fn scales(&self) -> Scales {
+        Scales {
+            log_size: self.log_size,
+            log_time: self.log_time,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// Report written by `bench` (the `.report.json` file)
+    pub report: PathBuf,
+    /// Directory the charts are written to
+    #[arg(short, long, default_value = ".")]
+    pub output_dir: PathBuf,
+    #[command(flatten)]
+    pub chart: ChartArgs,
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// Reports written by `bench`
+    #[arg(required = true)]
+    pub reports: Vec<PathBuf>,
+    /// Directory the charts are written to
+    #[arg(short, long, default_value = ".")]
+    pub output_dir: PathBuf,
+    #[command(flatten)]
+    pub chart: ChartArgs,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Report the changes are measured against
+    pub baseline: PathBuf,
+    /// Report of the new run
+    pub new: PathBuf,
+    /// Smallest relative change of the compression ratio that is reported
+    #[arg(long, default_value_t = 0.005)]
+    pub ratio_threshold: f64,
+    /// Smallest relative change of the median times that is reported, changes also need
+    /// non-overlapping confidence intervals
+    #[arg(long, default_value_t = 0.05)]
+    pub time_threshold: f64,
+}
+
+impl DiffArgs {
+    pub fn thresholds(&self) -> Thresholds {
+        Thresholds {
+            ratio: self.ratio_threshold,
+            time: self.time_threshold,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct TrainDictArgs {
+    /// Directory of .rnote files to train on
+    #[arg(long, default_value = "./files/dict/")]
+    pub corpus: PathBuf,
+    /// Maximum size of the dictionary, in bytes
+    #[arg(long, default_value_t = 2 * 1024 * 1024)]
+    pub max_size: usize,
+    #[command(flatten)]
+    pub train: TrainArgs,
+    /// Where the dictionary is written to, the description of its training set is written next to it
+    #[arg(short, long, default_value = "rnote.dict")]
+    pub output: PathBuf,
+}
+
+/// Training parameters shared by `train-dict` and `dict-sweep`
+#[derive(Args)]
+pub struct TrainArgs {
+    /// Extension of the files to train on
+    #[arg(long, default_value = "rnote")]
+    pub extension: String,
+    /// Notes are split into samples of at most this many bytes
+    #[arg(long, default_value_t = 16 * 1024)]
+    pub sample_size: usize,
+    /// Maximum total size of the samples in bytes, samples are dropped evenly across the notes above it
+    #[arg(long, default_value_t = 256 * 1024 * 1024)]
+    pub max_samples_size: usize,
+    #[arg(long, value_enum, default_value_t = Algorithm::FastCover)]
+    pub algorithm: Algorithm,
+    /// Segment size, 0 tries values between 50 and 2000
+    #[arg(short, long, default_value_t = 0)]
+    pub k: u32,
+    /// Dmer size, 0 tries 6 and 8
+    #[arg(short, long, default_value_t = 0)]
+    pub d: u32,
+    /// Number of (k, d) combinations to try, 0 for the zstd default
+    #[arg(long, default_value_t = 0)]
+    pub steps: u32,
+    /// log2 of the fastCOVER frequency table size, 0 for the zstd default
+    #[arg(short, long, default_value_t = 0)]
+    pub f: u32,
+    /// fastCOVER acceleration (1 to 10), 0 for the zstd default
+    #[arg(long, default_value_t = 0)]
+    pub accel: u32,
+    /// Compression level the dictionary is tuned for
+    #[arg(short, long, default_value_t = 9)]
+    pub level: i32,
+    /// Dictionary ID, 0 derives it from a hash of the dictionary content
+    #[arg(long, default_value_t = 0)]
+    pub dict_id: u32,
+}
+
+impl TrainArgs {
+    pub fn params(&self, dict_size: usize) -> Params {
+        Params {
+            dict_size,
+            extension: self.extension.clone(),
+            sample_size: self.sample_size,
+            max_samples_size: self.max_samples_size,
+            algorithm: self.algorithm,
+            k: self.k,
+            d: self.d,
+            steps: self.steps,
+            f: self.f,
+            accel: self.accel,
+            level: self.level,
+            dict_id: self.dict_id,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct DictSweepArgs {
+    /// Dictionary sizes to train, in bytes, in any order
+    #[arg(long, value_delimiter = ',', default_values_t = [16 << 10, 64 << 10, 256 << 10, 1 << 20, 2 << 20, 4 << 20])]
+    pub sizes: Vec<usize>,
+    /// Directory of .rnote files to train on, they must not be part of the corpus
+    #[arg(long, default_value = "./files/dict/")]
+    pub training: PathBuf,
+    #[command(flatten)]
+    pub train: TrainArgs,
+    /// Number of samples per input
+    #[arg(short = 'n', long, default_value_t = 8, value_parser = clap::value_parser!(u8).range(1..))]
+    pub samples: u8,
+    /// Warm-up before every timing series, see `bench --help`
+    #[arg(long, default_value = "1", value_parser = parse_warmup)]
+    pub warmup: Warmup,
+    /// Directory the chart and the report are written to
+    #[arg(short, long, default_value = ".")]
+    pub output_dir: PathBuf,
+    #[command(flatten)]
+    pub chart: ChartArgs,
+    /// The held-out notes the dictionaries are measured on
+    #[command(flatten)]
+    pub corpus: CorpusArgs,
+}
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// Also print the zstd frame headers of every input
+    #[arg(long)]
+    pub zstd_frames: bool,
+    #[command(flatten)]
+    pub corpus: CorpusArgs,
+}
@@ -3,16 +3,17 @@ use gzp::{
     par::compress::{ParCompress, ParCompressBuilder},
     Compression, ZWriter,
 };
+use liblzma::stream::Check;
 use std::io::Write;
 
-pub type CompFunc = Box<dyn Fn(&[u8]) -> Vec<u8>>;
+pub type CompFunc = Box<dyn Fn(&[u8]) -> anyhow::Result<Vec<u8>>>;
 
 pub fn gzip(level: u32) -> CompFunc {
     Box::new(move |data: &[u8]| {
         let mut encoder =
             flate2::write::GzEncoder::new(Vec::<u8>::new(), flate2::Compression::new(level));
-        encoder.write_all(data).unwrap();
-        encoder.finish().unwrap()
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
     })
 }
 
@@ -21,26 +22,109 @@ pub fn brotli(level: u32, buffer_size: usize, window_size: u32) -> CompFunc {
         let mut compressed: Vec<u8> = Vec::new();
         let mut encoder =
             brotli::CompressorWriter::new(&mut compressed, buffer_size, level, window_size);
-        encoder.write_all(data).unwrap();
+        encoder.write_all(data)?;
         drop(encoder);
-        compressed
+        Ok(compressed)
     })
 }
 
+/// The input size is pledged by every zstd codec, zstd then tunes its parameters to the input
+/// and writes the content size into the frame header
 pub fn zstd(level: i32) -> CompFunc {
     Box::new(move |data: &[u8]| {
-        let mut encoder = zstd::Encoder::new(Vec::<u8>::new(), level).unwrap();
-        encoder.write_all(data).unwrap();
-        encoder.finish().unwrap()
+        let mut encoder = zstd::Encoder::new(Vec::<u8>::new(), level)?;
+        encoder.set_pledged_src_size(Some(data.len() as u64))?;
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    })
+}
+
+/// The dictionary is prepared once, not on every call, the input size is pledged as otherwise
+/// zstd falls back to the parameters the dictionary was tuned for (inputs of a few hundred bytes)
+pub fn zstd_dict(level: i32, dict: &[u8]) -> CompFunc {
+    let dict = zstd::dict::EncoderDictionary::copy(dict, level);
+    Box::new(move |data: &[u8]| {
+        let mut encoder = zstd::Encoder::with_prepared_dictionary(Vec::<u8>::new(), &dict)?;
+        encoder.set_pledged_src_size(Some(data.len() as u64))?;
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
     })
 }
 
 pub fn par_zstd(level: i32, num_workers: u32) -> CompFunc {
     Box::new(move |data: &[u8]| {
-        let mut encoder = zstd::Encoder::new(Vec::<u8>::new(), level).unwrap();
-        encoder.multithread(num_workers).unwrap();
-        encoder.write_all(data).unwrap();
-        encoder.finish().unwrap()
+        let mut encoder = zstd::Encoder::new(Vec::<u8>::new(), level)?;
+        encoder.multithread(num_workers)?;
+        encoder.set_pledged_src_size(Some(data.len() as u64))?;
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    })
+}
+
+/// LZ4 frame format, levels below 3 use the fast compressor, 3 to 12 the high-compression one
+pub fn lz4(level: u32) -> CompFunc {
+    Box::new(move |data: &[u8]| {
+        let mut encoder = lz4::EncoderBuilder::new()
+            .level(level)
+            .build(Vec::<u8>::new())?;
+        encoder.write_all(data)?;
+        let (compressed, result) = encoder.finish();
+        result?;
+        Ok(compressed)
+    })
+}
+
+/// Snappy frame format
+pub fn snappy() -> CompFunc {
+    Box::new(move |data: &[u8]| {
+        let mut encoder = snap::write::FrameEncoder::new(Vec::<u8>::new());
+        encoder.write_all(data)?;
+        encoder
+            .into_inner()
+            .map_err(|e| anyhow::anyhow!("snappy: {}", e.error()))
+    })
+}
+
+/// `preset` goes from 0 to 9, `extreme` trades more time for a slightly better ratio
+pub fn xz(preset: u32, extreme: bool) -> CompFunc {
+    let preset = xz_preset(preset, extreme);
+    Box::new(move |data: &[u8]| {
+        let stream = liblzma::stream::Stream::new_easy_encoder(preset, Check::Crc64)?;
+        let mut encoder = liblzma::write::XzEncoder::new_stream(Vec::<u8>::new(), stream);
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    })
+}
+
+/// Splits the input into blocks compressed in parallel, small inputs fit in a single block
+pub fn par_xz(preset: u32, extreme: bool, num_workers: u32) -> CompFunc {
+    let preset = xz_preset(preset, extreme);
+    Box::new(move |data: &[u8]| {
+        let stream = liblzma::stream::MtStreamBuilder::new()
+            .preset(preset)
+            .check(Check::Crc64)
+            .threads(num_workers)
+            .encoder()?;
+        let mut encoder = liblzma::write::XzEncoder::new_stream(Vec::<u8>::new(), stream);
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
+    })
+}
+
+fn xz_preset(preset: u32, extreme: bool) -> u32 {
+    if extreme {
+        preset | liblzma::stream::PRESET_EXTREME
+    } else {
+        preset
+    }
+}
+
+pub fn bzip2(level: u32) -> CompFunc {
+    Box::new(move |data: &[u8]| {
+        let mut encoder =
+            bzip2::write::BzEncoder::new(Vec::<u8>::new(), bzip2::Compression::new(level));
+        encoder.write_all(data)?;
+        Ok(encoder.finish()?)
     })
 }
 
@@ -50,14 +134,14 @@ pub fn par_gzip(level: u32) -> CompFunc {
         let mut encoder: ParCompress<Gzip> = ParCompressBuilder::new()
             .compression_level(Compression::new(level))
             .from_writer(compressed.clone());
-        encoder.write_all(data).unwrap();
-        encoder.finish().unwrap();
-        compressed.extract_clone()
+        encoder.write_all(data)?;
+        encoder.finish()?;
+        Ok(compressed.extract_clone())
     })
 }
 
 pub fn par_zstd_opt() -> CompFunc {
-    Box::new(move |data: &[u8]| crate::zstd_impl::compress_to_zstd(data).unwrap())
+    Box::new(crate::zstd_impl::compress_to_zstd)
 }
 
 use std::sync::{Arc, RwLock};
@@ -0,0 +1,100 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+/// A decompressed `.rnote` file used as benchmark input
+pub struct Input {
+    /// the file name, or the path when several files of the corpus share it
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Loads every `.rnote` file found in the given sources, each source being either a directory
+/// (searched non-recursively) or a glob pattern, the inputs are sorted by decompressed size
+pub fn load<S: AsRef<str>>(sources: &[S]) -> anyhow::Result<Vec<Input>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for source in sources {
+        let source = source.as_ref();
+        let found = if Path::new(source).is_dir() {
+            from_dir(Path::new(source), "rnote")?
+        } else {
+            from_glob(source)?
+        };
+        if found.is_empty() {
+            eprintln!("no .rnote files found in '{}'", source);
+        }
+        paths.extend(found);
+    }
+    // the same file can be matched by several sources
+    paths.sort();
+    paths.dedup();
+
+    if paths.is_empty() {
+        anyhow::bail!("the corpus is empty");
+    }
+
+    // results are matched by input name, files sharing a name in different sources keep their path
+    let file_name = |path: &PathBuf| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string())
+    };
+    let names: Vec<String> = paths.iter().map(file_name).collect();
+    let mut inputs = paths
+        .iter()
+        .zip(names.iter())
+        .map(|(path, name)| {
+            let mut compressed: Vec<u8> = Vec::new();
+            std::fs::File::open(path)
+                .and_then(|mut file| file.read_to_end(&mut compressed))
+                .with_context(|| format!("failed to read '{}'", path.display()))?;
+            Ok(Input {
+                name: if names.iter().filter(|other| *other == name).count() > 1 {
+                    path.display().to_string()
+                } else {
+                    name.clone()
+                },
+                data: crate::utils::decompress_default(&compressed)
+                    .with_context(|| format!("failed to decompress '{}'", path.display()))?,
+            })
+        })
+        .collect::<anyhow::Result<Vec<Input>>>()?;
+    inputs.sort_by_key(|input| input.data.len());
+
+    Ok(inputs)
+}
+
+/// Files of `dir` (non-recursively) with the given extension, in directory order
+pub fn from_dir(dir: &Path, extension: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for entry in dir
+        .read_dir()
+        .with_context(|| format!("failed to read directory '{}'", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_file() && has_extension(&path, extension) {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+fn from_glob(pattern: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for entry in
+        glob::glob(pattern).with_context(|| format!("invalid glob pattern '{}'", pattern))?
+    {
+        let path = entry?;
+        if path.is_file() && has_extension(&path, "rnote") {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|ext| ext == extension)
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+/// User + system CPU time consumed by every thread of the process so far, `None` when unsupported
+#[cfg(unix)]
+pub fn process_time() -> Option<Duration> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    // SAFETY: getrusage only writes to the provided struct
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: getrusage succeeded, so the struct is initialized
+    let usage = unsafe { usage.assume_init() };
+    let timeval = |tv: libc::timeval| {
+        Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+    };
+    Some(timeval(usage.ru_utime) + timeval(usage.ru_stime))
+}
+
+#[cfg(not(unix))]
+pub fn process_time() -> Option<Duration> {
+    None
+}
@@ -1,16 +1,23 @@
 use std::io::Read;
 
-pub type DecompFunc = Box<dyn Fn(&[u8])>;
+pub type DecompFunc = Box<dyn Fn(&[u8]) -> anyhow::Result<Vec<u8>>>;
 
 pub fn gzip() -> DecompFunc {
     Box::new(move |compressed| {
         let mut bytes: Vec<u8> = {
             let mut decompressed_size: [u8; 4] = [0; 4];
-            decompressed_size.copy_from_slice(&compressed[compressed.len() - 4..]);
+            decompressed_size.copy_from_slice(
+                compressed
+                    .len()
+                    .checked_sub(4)
+                    .map(|isize_sidx| &compressed[isize_sidx..])
+                    .ok_or(anyhow::anyhow!("Not a valid gzip-compressed file"))?,
+            );
             Vec::with_capacity(u32::from_le_bytes(decompressed_size) as usize)
         };
         let mut decoder = flate2::read::MultiGzDecoder::new(compressed);
-        decoder.read_to_end(&mut bytes).unwrap();
+        decoder.read_to_end(&mut bytes)?;
+        Ok(bytes)
     })
 }
 
@@ -18,20 +25,67 @@ pub fn brotli() -> DecompFunc {
     Box::new(move |compressed| {
         let mut bytes: Vec<u8> = Vec::new();
         let mut decoder = brotli::Decompressor::new(compressed, 4096);
-        decoder.read_to_end(&mut bytes).unwrap();
+        decoder.read_to_end(&mut bytes)?;
+        Ok(bytes)
     })
 }
 
 pub fn zstd() -> DecompFunc {
     Box::new(move |compressed| {
         let mut bytes: Vec<u8> = Vec::new();
-        let mut decoder = zstd::Decoder::new(compressed).unwrap();
-        decoder.read_to_end(&mut bytes).unwrap();
+        let mut decoder = zstd::Decoder::new(compressed)?;
+        decoder.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    })
+}
+
+/// The dictionary is prepared once, not on every call
+pub fn zstd_dict(dict: &[u8]) -> DecompFunc {
+    let dict = zstd::dict::DecoderDictionary::copy(dict);
+    Box::new(move |compressed| {
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut decoder = zstd::Decoder::with_prepared_dictionary(compressed, &dict)?;
+        decoder.read_to_end(&mut bytes)?;
+        Ok(bytes)
     })
 }
 
 pub fn zstd_opt() -> DecompFunc {
+    Box::new(crate::zstd_impl::decompress_from_zstd)
+}
+
+pub fn lz4() -> DecompFunc {
+    Box::new(move |compressed| {
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut decoder = lz4::Decoder::new(compressed)?;
+        decoder.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    })
+}
+
+pub fn snappy() -> DecompFunc {
     Box::new(move |compressed| {
-        crate::zstd_impl::decompress_from_zstd(compressed).unwrap();
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut decoder = snap::read::FrameDecoder::new(compressed);
+        decoder.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    })
+}
+
+pub fn xz() -> DecompFunc {
+    Box::new(move |compressed| {
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut decoder = liblzma::read::XzDecoder::new(compressed);
+        decoder.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    })
+}
+
+pub fn bzip2() -> DecompFunc {
+    Box::new(move |compressed| {
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut decoder = bzip2::read::MultiBzDecoder::new(compressed);
+        decoder.read_to_end(&mut bytes)?;
+        Ok(bytes)
     })
 }
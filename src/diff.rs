@@ -0,0 +1,258 @@
+use crate::{bfunc::BenchResult, report::BenchReport, stats::Summary};
+
+/// Smallest relative changes that count, anything below is treated as noise
+#[derive(Clone, Copy)]
+pub struct Thresholds {
+    pub ratio: f64,
+    pub time: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change {
+    Unchanged,
+    Improved,
+    Regressed,
+}
+
+/// How one metric of a (codec, input) pair moved between the two runs
+pub struct Delta {
+    pub baseline: f64,
+    pub new: f64,
+    pub change: Change,
+}
+
+impl Delta {
+    /// relative to the baseline
+    pub fn relative(&self) -> f64 {
+        self.new / self.baseline - 1.0
+    }
+
+    /// A higher ratio is better, a change is only flagged above the threshold
+    fn ratio(baseline: &BenchResult, new: &BenchResult, threshold: f64) -> Self {
+        let mut delta = Self {
+            baseline: baseline.ratio(),
+            new: new.ratio(),
+            change: Change::Unchanged,
+        };
+        if delta.relative().abs() >= threshold {
+            delta.change = if delta.relative() > 0.0 {
+                Change::Improved
+            } else {
+                Change::Regressed
+            };
+        }
+        delta
+    }
+
+    /// A lower time is better, a change is only flagged above the threshold
+    /// and when the confidence intervals of the medians do not overlap
+    fn time(baseline: &Summary, new: &Summary, threshold: f64) -> Self {
+        let mut delta = Self {
+            baseline: baseline.median,
+            new: new.median,
+            change: Change::Unchanged,
+        };
+        let overlap = new.ci_low <= baseline.ci_high && baseline.ci_low <= new.ci_high;
+        if delta.relative().abs() >= threshold && !overlap {
+            delta.change = if delta.relative() < 0.0 {
+                Change::Improved
+            } else {
+                Change::Regressed
+            };
+        }
+        delta
+    }
+}
+
+pub struct Row {
+    pub codec: String,
+    pub input: String,
+    pub ratio: Delta,
+    pub comp_time: Delta,
+    pub decomp_time: Delta,
+}
+
+impl Row {
+    pub fn is_regression(&self) -> bool {
+        [&self.ratio, &self.comp_time, &self.decomp_time]
+            .iter()
+            .any(|delta| delta.change == Change::Regressed)
+    }
+}
+
+pub struct Diff {
+    pub rows: Vec<Row>,
+    /// (codec, input) pairs measured in only one of the runs
+    pub only_in_baseline: Vec<(String, String)>,
+    pub only_in_new: Vec<(String, String)>,
+    /// pairs whose input has the same name but different content, they are not compared
+    pub changed_inputs: Vec<(String, String)>,
+    /// pairs that succeeded in the baseline but failed in the new run, with the error,
+    /// they count as regressions
+    pub new_failures: Vec<(String, String, String)>,
+}
+
+impl Diff {
+    /// Matches the results of both reports by codec title and input name
+    pub fn new(baseline: &BenchReport, new: &BenchReport, thresholds: Thresholds) -> Self {
+        let find = |report: &BenchReport, result: &BenchResult| {
+            report
+                .results()
+                .any(|r| r.title == result.title && r.input_name == result.input_name)
+        };
+        let key = |result: &BenchResult| (result.title.clone(), result.input_name.clone());
+
+        let mut rows = Vec::new();
+        let mut changed_inputs = Vec::new();
+        for old in baseline.results() {
+            let Some(new) = new
+                .results()
+                .find(|r| r.title == old.title && r.input_name == old.input_name)
+            else {
+                continue;
+            };
+            if old.input_hash != new.input_hash {
+                changed_inputs.push(key(old));
+                continue;
+            }
+            rows.push(Row {
+                codec: old.title.clone(),
+                input: old.input_name.clone(),
+                ratio: Delta::ratio(old, new, thresholds.ratio),
+                comp_time: Delta::time(&old.comp_time, &new.comp_time, thresholds.time),
+                decomp_time: Delta::time(&old.decomp_time, &new.decomp_time, thresholds.time),
+            });
+        }
+
+        let failed = |result: &BenchResult| {
+            new.failures
+                .iter()
+                .find(|f| f.title == result.title && f.input_name == result.input_name)
+        };
+        let new_failures = baseline
+            .results()
+            .filter_map(|r| {
+                failed(r).map(|f| (r.title.clone(), r.input_name.clone(), f.error.clone()))
+            })
+            .collect();
+
+        Self {
+            rows,
+            only_in_baseline: baseline
+                .results()
+                .filter(|r| !find(new, r) && failed(r).is_none())
+                .map(key)
+                .collect(),
+            only_in_new: new
+                .results()
+                .filter(|r| !find(baseline, r))
+                .map(key)
+                .collect(),
+            changed_inputs,
+            new_failures,
+        }
+    }
+
+    pub fn num_of_regressions(&self) -> usize {
+        self.rows.iter().filter(|row| row.is_regression()).count() + self.new_failures.len()
+    }
+
+    /// compared pairs and pairs that failed in the new run
+    pub fn num_of_pairs(&self) -> usize {
+        self.rows.len() + self.new_failures.len()
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{:<28} {:<24} {:>22} {:>22} {:>22}",
+            "codec", "input", "ratio", "comp time", "decomp time"
+        );
+        for row in self.rows.iter() {
+            println!(
+                "{:<28} {:<24} {:>22} {:>22} {:>22}",
+                row.codec,
+                row.input,
+                cell(&row.ratio),
+                cell(&row.comp_time),
+                cell(&row.decomp_time),
+            );
+        }
+
+        if !self.new_failures.is_empty() {
+            println!("\nfailed in the new run (WORSE):");
+            for (codec, input, error) in self.new_failures.iter() {
+                println!("* '{}' on '{}': {}", codec, input, error);
+            }
+        }
+
+        let lists = [
+            ("only in the baseline", &self.only_in_baseline),
+            ("only in the new run", &self.only_in_new),
+            ("input changed, not compared", &self.changed_inputs),
+        ];
+        for (desc, pairs) in lists {
+            if pairs.is_empty() {
+                continue;
+            }
+            println!("\n{}:", desc);
+            for (codec, input) in pairs.iter() {
+                println!("* '{}' on '{}'", codec, input);
+            }
+        }
+    }
+}
+
+fn cell(delta: &Delta) -> String {
+    let mark = match delta.change {
+        Change::Unchanged => "",
+        Change::Improved => " (better)",
+        Change::Regressed => " (WORSE)",
+    };
+    format!("{:+.1}%{}", delta.relative() * 100.0, mark)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn summary(millis: &[u64]) -> Summary {
+        Summary::new(
+            &millis
+                .iter()
+                .copied()
+                .map(Duration::from_millis)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn slower_time_regresses() {
+        let delta = Delta::time(&summary(&[100; 8]), &summary(&[120; 8]), 0.05);
+        assert!((delta.relative() - 0.2).abs() < 1e-9);
+        assert!(delta.change == Change::Regressed);
+    }
+
+    #[test]
+    fn faster_time_improves() {
+        let delta = Delta::time(&summary(&[100; 8]), &summary(&[80; 8]), 0.05);
+        assert!(delta.change == Change::Improved);
+    }
+
+    #[test]
+    fn change_below_threshold_is_noise() {
+        let delta = Delta::time(&summary(&[100; 8]), &summary(&[103; 8]), 0.05);
+        assert!(delta.change == Change::Unchanged);
+    }
+
+    #[test]
+    fn overlapping_intervals_are_noise() {
+        // the medians differ by 20% but the samples overlap widely
+        let baseline = summary(&[50, 60, 100, 110, 150, 160, 170]);
+        let new = summary(&[50, 60, 100, 130, 150, 160, 170]);
+        assert!(baseline.ci_high >= new.ci_low);
+        let delta = Delta::time(&baseline, &new, 0.05);
+        assert!(delta.relative() > 0.05);
+        assert!(delta.change == Change::Unchanged);
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::{bfunc::BenchResult, report::BenchReport};
+
+/// One row of the exported results, times are in seconds, throughputs in MB/s and memory in bytes,
+/// `ci_low`/`ci_high` bound the 95% bootstrap confidence interval of the median,
+/// CPU times, parallelism and memory usage are left empty where they cannot be measured
+#[derive(Serialize)]
+struct Record<'a> {
+    codec: &'a str,
+    input: &'a str,
+    input_crc32: String,
+    uncompressed_size: usize,
+    compressed_size: usize,
+    ratio: f64,
+    median_comp_time: f64,
+    min_comp_time: f64,
+    max_comp_time: f64,
+    mean_comp_time: f64,
+    std_dev_comp_time: f64,
+    mad_comp_time: f64,
+    p5_comp_time: f64,
+    p95_comp_time: f64,
+    ci_low_comp_time: f64,
+    ci_high_comp_time: f64,
+    median_decomp_time: f64,
+    min_decomp_time: f64,
+    max_decomp_time: f64,
+    mean_decomp_time: f64,
+    std_dev_decomp_time: f64,
+    mad_decomp_time: f64,
+    p5_decomp_time: f64,
+    p95_decomp_time: f64,
+    ci_low_decomp_time: f64,
+    ci_high_decomp_time: f64,
+    comp_throughput: f64,
+    decomp_throughput: f64,
+    median_comp_cpu_time: Option<f64>,
+    comp_parallelism: Option<f64>,
+    median_decomp_cpu_time: Option<f64>,
+    decomp_parallelism: Option<f64>,
+    comp_peak_memory: Option<usize>,
+    comp_total_allocated: Option<usize>,
+    decomp_peak_memory: Option<usize>,
+    decomp_total_allocated: Option<usize>,
+    num_of_comp_samples: usize,
+    num_of_decomp_samples: usize,
+}
+
+impl<'a> From<&'a BenchResult> for Record<'a> {
+    fn from(result: &'a BenchResult) -> Self {
+        Self {
+            codec: &result.title,
+            input: &result.input_name,
+            input_crc32: format!("{:08x}", result.input_hash),
+            uncompressed_size: result.uncompressed_size,
+            compressed_size: result.compressed_size,
+            ratio: result.ratio(),
+            median_comp_time: result.comp_time.median,
+            min_comp_time: result.comp_time.min,
+            max_comp_time: result.comp_time.max,
+            mean_comp_time: result.comp_time.mean,
+            std_dev_comp_time: result.comp_time.std_dev,
+            mad_comp_time: result.comp_time.mad,
+            p5_comp_time: result.comp_time.p5,
+            p95_comp_time: result.comp_time.p95,
+            ci_low_comp_time: result.comp_time.ci_low,
+            ci_high_comp_time: result.comp_time.ci_high,
+            median_decomp_time: result.decomp_time.median,
+            min_decomp_time: result.decomp_time.min,
+            max_decomp_time: result.decomp_time.max,
+            mean_decomp_time: result.decomp_time.mean,
+            std_dev_decomp_time: result.decomp_time.std_dev,
+            mad_decomp_time: result.decomp_time.mad,
+            p5_decomp_time: result.decomp_time.p5,
+            p95_decomp_time: result.decomp_time.p95,
+            ci_low_decomp_time: result.decomp_time.ci_low,
+            ci_high_decomp_time: result.decomp_time.ci_high,
+            comp_throughput: result.comp_throughput(),
+            decomp_throughput: result.decomp_throughput(),
+            median_comp_cpu_time: result
+                .comp_cpu_time
+                .as_ref()
+                .map(|cpu_time| cpu_time.median),
+            comp_parallelism: result.comp_parallelism(),
+            median_decomp_cpu_time: result
+                .decomp_cpu_time
+                .as_ref()
+                .map(|cpu_time| cpu_time.median),
+            decomp_parallelism: result.decomp_parallelism(),
+            comp_peak_memory: result.comp_memory.peak,
+            comp_total_allocated: result.comp_memory.total,
+            decomp_peak_memory: result.decomp_memory.peak,
+            decomp_total_allocated: result.decomp_memory.total,
+            num_of_comp_samples: result.comp_time.num_of_samples,
+            num_of_decomp_samples: result.decomp_time.num_of_samples,
+        }
+    }
+}
+
+pub fn write_csv(path: &Path, report: &BenchReport) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    for result in report.results() {
+        writer.serialize(Record::from(result))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_json(path: &Path, report: &BenchReport) -> anyhow::Result<()> {
+    let records: Vec<Record> = report.results().map(Record::from).collect();
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create '{}'", path.display()))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), &records)?;
+    Ok(())
+}
@@ -1,6 +1,11 @@
-use plotters::prelude::*;
+use plotters::{
+    coord::{ranged1d::ValueFormatter, Shift},
+    element::{Drawable, PointCollection},
+    prelude::*,
+};
+use std::ops::Range;
 
-pub const COLOR_WHEEL: [RGBColor; 19] = [
+const COLOR_WHEEL: [RGBColor; 19] = [
     RGBColor(0x00, 0xff, 0xff),
     RGBColor(0x4b, 0x00, 0x82),
     RGBColor(0xd2, 0x69, 0x3e),
@@ -22,6 +27,421 @@ pub const COLOR_WHEEL: [RGBColor; 19] = [
     RGBColor(0xfa, 0x80, 0x72),
 ];
 
+/// What an axis shows, decides whether it is drawn in log scale
+#[derive(Clone, Copy)]
+pub enum Quantity {
+    Size,
+    Time,
+    /// never drawn in log scale
+    Ratio,
+    /// follows the scale of the times
+    Throughput,
+    /// relative change, can be negative, never drawn in log scale
+    Gain,
+}
+
+impl Quantity {
+    /// Sizes and times are read against zero, ratios and throughputs are compared to each other
+    fn starts_at_zero(&self) -> bool {
+        match self {
+            Quantity::Size | Quantity::Time => true,
+            Quantity::Ratio | Quantity::Throughput | Quantity::Gain => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Scales {
+    pub log_size: bool,
+    pub log_time: bool,
+}
+
+impl Scales {
+    pub fn is_log(&self, quantity: Quantity) -> bool {
+        match quantity {
+            Quantity::Size => self.log_size,
+            Quantity::Time | Quantity::Throughput => self.log_time,
+            Quantity::Ratio | Quantity::Gain => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Axis<'a> {
+    pub desc: &'a str,
+    pub quantity: Quantity,
+}
+
+/// Shapes marking the measured points, to tell apart series sharing a colour
+#[derive(Clone, Copy)]
+pub enum Marker {
+    Circle,
+    Square,
+    Triangle,
+    Cross,
+}
+
+pub const MARKERS: [Marker; 4] = [
+    Marker::Circle,
+    Marker::Square,
+    Marker::Triangle,
+    Marker::Cross,
+];
+
+/// Line patterns, the last resort to tell series apart
+#[derive(Clone, Copy)]
+pub enum Dash {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+pub const DASHES: [Dash; 3] = [Dash::Solid, Dash::Dashed, Dash::Dotted];
+
+impl Dash {
+    /// Whether the `idx`th point of an interpolated line is drawn
+    fn shows(&self, idx: usize) -> bool {
+        match self {
+            Dash::Solid => true,
+            Dash::Dashed => idx % 90 < 60,
+            Dash::Dotted => idx % 40 < 10,
+        }
+    }
+
+    /// `stroke-dasharray` of the pattern
+    pub fn svg(&self) -> &'static str {
+        match self {
+            Dash::Solid => "none",
+            Dash::Dashed => "8 4",
+            Dash::Dotted => "2 4",
+        }
+    }
+}
+
+/// Colours of the series
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum Palette {
+    /// the original colour wheel, extended with generated colours
+    #[default]
+    Default,
+    /// the Okabe-Ito colours, distinguishable with every common colour vision deficiency
+    ColorBlind,
+}
+
+/// Okabe-Ito palette
+const COLOR_BLIND: [RGBColor; 8] = [
+    RGBColor(0x00, 0x00, 0x00),
+    RGBColor(0xe6, 0x9f, 0x00),
+    RGBColor(0x56, 0xb4, 0xe9),
+    RGBColor(0x00, 0x9e, 0x73),
+    RGBColor(0xf0, 0xe4, 0x42),
+    RGBColor(0x00, 0x72, 0xb2),
+    RGBColor(0xd5, 0x5e, 0x00),
+    RGBColor(0xcc, 0x79, 0xa7),
+];
+
+/// Colour of the reference lines (Pareto frontier, no gain), in no palette
+pub const REFERENCE: RGBColor = RGBColor(0x80, 0x80, 0x80);
+
+/// How a series is drawn
+#[derive(Clone, Copy)]
+pub struct Style {
+    pub color: RGBColor,
+    /// `None` only draws the line
+    pub marker: Option<Marker>,
+    pub dash: Dash,
+}
+
+impl Palette {
+    /// Style of the `idx`th series
+    ///
+    /// Once the base colours are used up, the colours start over with a marker, then with
+    /// every other marker and finally with another dash pattern. The default palette keeps
+    /// generating new colours on top of that so every index gets a different style, the
+    /// colour-blind one only reuses its own and repeats after 8 × 5 × 3 = 120 series.
+    pub fn style(&self, idx: usize) -> Style {
+        let base = match self {
+            Palette::Default => COLOR_WHEEL.len(),
+            Palette::ColorBlind => COLOR_BLIND.len(),
+        };
+        let round = idx / base;
+        let color = match self {
+            Palette::Default => COLOR_WHEEL
+                .get(idx)
+                .copied()
+                .unwrap_or_else(|| generated(idx)),
+            Palette::ColorBlind => COLOR_BLIND[idx % base],
+        };
+        Style {
+            color,
+            marker: match round % (MARKERS.len() + 1) {
+                0 => None,
+                n => Some(MARKERS[n - 1]),
+            },
+            dash: DASHES[round / (MARKERS.len() + 1) % DASHES.len()],
+        }
+    }
+}
+
+/// Hues spaced by the golden angle never repeat and stay far apart from their neighbours,
+/// the lightness cycles so that close hues still differ
+fn generated(idx: usize) -> RGBColor {
+    let hue = (idx as f64 * 137.507_764) % 360.0;
+    let lightness = [0.35, 0.5, 0.65][idx % 3];
+    let saturation = 0.75;
+
+    // HSL to RGB
+    let chroma = (1.0 - (2.0 * lightness - 1.0_f64).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |c: f64| ((c + m) * 255.0).round() as u8;
+    RGBColor(channel(r), channel(g), channel(b))
+}
+
+pub struct Series<'a> {
+    pub label: &'a str,
+    pub style: Style,
+    pub points: Vec<(f64, f64)>,
+    /// name of the input behind every point
+    pub names: Vec<&'a str>,
+}
+
+/// Everything drawn on one panel
+pub struct Chart<'a> {
+    pub caption: &'a str,
+    pub x: Axis<'a>,
+    pub y: Axis<'a>,
+    pub series: Vec<Series<'a>>,
+    /// text drawn next to a point
+    pub annotations: Vec<((f64, f64), String)>,
+    /// where the legend is drawn, the HTML output puts it under the chart
+    pub legend: SeriesLabelPosition,
+}
+
+impl Chart<'_> {
+    pub fn x_range(&self, scales: Scales) -> Range<f64> {
+        axis_range(
+            self.series
+                .iter()
+                .flat_map(|s| s.points.iter().map(|p| p.0)),
+            self.x.quantity,
+            scales.is_log(self.x.quantity),
+        )
+    }
+
+    pub fn y_range(&self, scales: Scales) -> Range<f64> {
+        axis_range(
+            self.series
+                .iter()
+                .flat_map(|s| s.points.iter().map(|p| p.1)),
+            self.y.quantity,
+            scales.is_log(self.y.quantity),
+        )
+    }
+}
+
+/// Draws one chart, the axis ranges are fitted to the points of every series
+pub fn draw_panel<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    content: &Chart,
+    scales: Scales,
+) -> anyhow::Result<()> {
+    let (x, y) = (content.x, content.y);
+    let x_range = content.x_range(scales);
+    let y_range = content.y_range(scales);
+
+    let mut builder = ChartBuilder::on(area);
+    builder
+        .caption(content.caption, ("sans-serif", 25).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(50);
+
+    // log scaled ranges are different types, hence the four branches
+    match (scales.is_log(x.quantity), scales.is_log(y.quantity)) {
+        (false, false) => fill_chart(
+            builder
+                .build_cartesian_2d(x_range, y_range)
+                .map_err(draw_error)?,
+            content,
+        )?,
+        (true, false) => fill_chart(
+            builder
+                .build_cartesian_2d(x_range.log_scale(), y_range)
+                .map_err(draw_error)?,
+            content,
+        )?,
+        (false, true) => fill_chart(
+            builder
+                .build_cartesian_2d(x_range, y_range.log_scale())
+                .map_err(draw_error)?,
+            content,
+        )?,
+        (true, true) => fill_chart(
+            builder
+                .build_cartesian_2d(x_range.log_scale(), y_range.log_scale())
+                .map_err(draw_error)?,
+            content,
+        )?,
+    }
+    Ok(())
+}
+
+fn fill_chart<'a, DB, X, Y>(
+    mut chart: ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    content: &Chart,
+) -> anyhow::Result<()>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64> + ValueFormatter<f64>,
+    Y: Ranged<ValueType = f64> + ValueFormatter<f64>,
+{
+    chart
+        .configure_mesh()
+        .x_desc(content.x.desc)
+        .y_desc(content.y.desc)
+        .axis_desc_style(("sans-serif", 20).into_font())
+        .draw()
+        .map_err(draw_error)?;
+
+    for s in content.series.iter() {
+        let color = s.style.color;
+        let dash = s.style.dash;
+        let line = chart
+            .draw_series(
+                interpolation::linear(s.points.clone())
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(idx, _)| dash.shows(*idx))
+                    .map(|(_, coord)| Circle::new(coord, 1, color.stroke_width(1))),
+            )
+            .map_err(draw_error)?;
+        let style = color.stroke_width(2);
+        match s.style.marker {
+            None => {
+                line.label(s.label)
+                    .legend(move |(x, y)| Circle::new((x + 10, y), 7, style));
+            }
+            Some(Marker::Circle) => draw_markers(
+                &mut chart,
+                s,
+                |coord| Circle::new(coord, 5, style),
+                move |(x, y)| Circle::new((x + 10, y), 7, style),
+            )?,
+            Some(Marker::Square) => draw_markers(
+                &mut chart,
+                s,
+                |coord| EmptyElement::at(coord) + Rectangle::new([(-4, -4), (4, 4)], style),
+                move |(x, y)| Rectangle::new([(x + 4, y - 6), (x + 16, y + 6)], style),
+            )?,
+            Some(Marker::Triangle) => draw_markers(
+                &mut chart,
+                s,
+                |coord| TriangleMarker::new(coord, 5, style),
+                move |(x, y)| TriangleMarker::new((x + 10, y), 7, style),
+            )?,
+            Some(Marker::Cross) => draw_markers(
+                &mut chart,
+                s,
+                |coord| Cross::new(coord, 5, style),
+                move |(x, y)| Cross::new((x + 10, y), 7, style),
+            )?,
+        }
+    }
+
+    chart
+        .draw_series(content.annotations.iter().map(|(coord, text)| {
+            EmptyElement::at(*coord) + Text::new(text.clone(), (8, -20), ("sans-serif", 16))
+        }))
+        .map_err(draw_error)?;
+
+    chart
+        .configure_series_labels()
+        .position(content.legend.clone())
+        // long sweeps would not fit into the panel otherwise
+        .label_font(
+            (
+                "sans-serif",
+                (300 / content.series.len().max(1)).clamp(10, 20) as u32,
+            )
+                .into_font(),
+        )
+        .border_style(BLACK)
+        .draw()
+        .map_err(draw_error)?;
+    Ok(())
+}
+
+/// Draws a marker on every point of the series, with the legend showing the same marker
+fn draw_markers<'a, DB, X, Y, E, L>(
+    chart: &mut ChartContext<'a, DB, Cartesian2d<X, Y>>,
+    s: &Series,
+    point: impl Fn((f64, f64)) -> E,
+    legend: impl Fn((i32, i32)) -> L + 'a,
+) -> anyhow::Result<()>
+where
+    DB: DrawingBackend + 'a,
+    X: Ranged<ValueType = f64>,
+    Y: Ranged<ValueType = f64>,
+    E: Drawable<DB>,
+    for<'b> &'b E: PointCollection<'b, (f64, f64)>,
+    L: IntoDynElement<'a, DB, (i32, i32)>,
+{
+    chart
+        .draw_series(s.points.iter().map(|coord| point(*coord)))
+        .map_err(draw_error)?
+        .label(s.label)
+        .legend(legend);
+    Ok(())
+}
+
+/// plotters errors are generic over the backend, they are turned into messages
+pub fn draw_error(e: impl std::fmt::Display) -> anyhow::Error {
+    anyhow::anyhow!("failed to draw the chart: {}", e)
+}
+
+/// Linear axes of sizes and times start at zero and get 5% of headroom,
+/// other linear axes get 5% of the span on both ends, log axes get 20% on both ends
+pub fn axis_range(values: impl Iterator<Item = f64>, quantity: Quantity, log: bool) -> Range<f64> {
+    let (min, max) = values
+        .filter(|v| v.is_finite() && (!log || *v > 0.0))
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+
+    if log {
+        if min > max {
+            return 1e-3..1.0;
+        }
+        min / 1.2..max * 1.2
+    } else {
+        if quantity.starts_at_zero() {
+            if max <= 0.0 {
+                return 0.0..1.0;
+            }
+            return 0.0..max * 1.05;
+        }
+        if min > max {
+            return 0.0..1.0;
+        }
+        // a single value still needs a range around it
+        let span = if max > min {
+            max - min
+        } else {
+            max.abs().max(1.0)
+        };
+        (min - span * 0.05)..(max + span * 0.05)
+    }
+}
+
 // taken from Syracuse
 pub mod interpolation {
     use itertools::Itertools;
@@ -0,0 +1,291 @@
+use std::{fmt::Write, ops::Range, path::Path};
+
+use anyhow::Context;
+use plotters::style::RGBColor;
+
+use crate::graph::{Chart, Marker, Scales, Series};
+
+const WIDTH: f64 = 1100.0;
+const HEIGHT: f64 = 550.0;
+// room for the tick labels and the axis descriptions
+const LEFT: f64 = 90.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 20.0;
+const BOTTOM: f64 = 60.0;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+svg { display: block; }
+.grid { stroke: #ddd; }
+.axis { stroke: black; }
+.point:hover { stroke-width: 5; }
+.legend { display: flex; flex-wrap: wrap; gap: 0.5em 1.5em; margin: 0.5em 0 2em 0; }
+.legend span { display: flex; align-items: center; gap: 0.3em; }";
+
+/// Writes the charts as a single HTML page without any external resources,
+/// every point carries a tooltip with its exact values
+pub fn write(
+    path: &Path,
+    title: &str,
+    environments: &[String],
+    charts: &[Chart],
+    scales: Scales,
+) -> anyhow::Result<()> {
+    let mut page = String::new();
+    writeln!(
+        page,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+    )?;
+    writeln!(page, "<title>{}</title>", escape(title))?;
+    writeln!(page, "<style>\n{}\n</style>\n</head>\n<body>", STYLE)?;
+    writeln!(page, "<h1>{}</h1>", escape(title))?;
+    for environment in environments {
+        writeln!(page, "<p>measured on {}</p>", escape(environment))?;
+    }
+    for chart in charts {
+        write_chart(&mut page, chart, scales)?;
+    }
+    writeln!(page, "</body>\n</html>")?;
+
+    std::fs::write(path, page).with_context(|| format!("failed to write '{}'", path.display()))
+}
+
+fn write_chart(page: &mut String, chart: &Chart, scales: Scales) -> anyhow::Result<()> {
+    let (x_log, y_log) = (
+        scales.is_log(chart.x.quantity),
+        scales.is_log(chart.y.quantity),
+    );
+    let visible = |(x, y): (f64, f64)| {
+        x.is_finite() && y.is_finite() && (!x_log || x > 0.0) && (!y_log || y > 0.0)
+    };
+    let x_range = chart.x_range(scales);
+    let y_range = chart.y_range(scales);
+    let to_px = |(x, y): (f64, f64)| {
+        (
+            LEFT + fraction(x, &x_range, x_log) * (WIDTH - LEFT - RIGHT),
+            HEIGHT - BOTTOM - fraction(y, &y_range, y_log) * (HEIGHT - TOP - BOTTOM),
+        )
+    };
+
+    writeln!(page, "<h2>{}</h2>", escape(chart.caption))?;
+    writeln!(
+        page,
+        "<svg width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"0 0 {WIDTH} {HEIGHT}\">"
+    )?;
+
+    // grid and tick labels
+    for tick in ticks(&x_range, x_log) {
+        let (px, _) = to_px((tick, y_range.start));
+        writeln!(
+            page,
+            "<line class=\"grid\" x1=\"{px:.1}\" y1=\"{TOP}\" x2=\"{px:.1}\" y2=\"{}\"/>",
+            HEIGHT - BOTTOM
+        )?;
+        writeln!(
+            page,
+            "<text x=\"{px:.1}\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\">{}</text>",
+            HEIGHT - BOTTOM + 16.0,
+            tick_label(tick, &x_range, x_log)
+        )?;
+    }
+    for tick in ticks(&y_range, y_log) {
+        let (_, py) = to_px((x_range.start, tick));
+        writeln!(
+            page,
+            "<line class=\"grid\" x1=\"{LEFT}\" y1=\"{py:.1}\" x2=\"{}\" y2=\"{py:.1}\"/>",
+            WIDTH - RIGHT
+        )?;
+        writeln!(
+            page,
+            "<text x=\"{}\" y=\"{:.1}\" font-size=\"12\" text-anchor=\"end\">{}</text>",
+            LEFT - 6.0,
+            py + 4.0,
+            tick_label(tick, &y_range, y_log)
+        )?;
+    }
+    writeln!(
+        page,
+        "<polyline class=\"axis\" fill=\"none\" points=\"{LEFT},{TOP} {LEFT},{bottom} {right},{bottom}\"/>",
+        bottom = HEIGHT - BOTTOM,
+        right = WIDTH - RIGHT
+    )?;
+    writeln!(
+        page,
+        "<text x=\"{}\" y=\"{}\" font-size=\"14\" text-anchor=\"middle\">{}</text>",
+        LEFT + (WIDTH - LEFT - RIGHT) / 2.0,
+        HEIGHT - 15.0,
+        escape(chart.x.desc)
+    )?;
+    writeln!(
+        page,
+        "<text transform=\"translate(20 {}) rotate(-90)\" font-size=\"14\" text-anchor=\"middle\">{}</text>",
+        TOP + (HEIGHT - TOP - BOTTOM) / 2.0,
+        escape(chart.y.desc)
+    )?;
+
+    for s in chart.series.iter() {
+        let color = hex(s.style.color);
+        let points: Vec<((f64, f64), &str)> = s
+            .points
+            .iter()
+            .copied()
+            .zip(s.names.iter().copied())
+            .filter(|(point, _)| visible(*point))
+            .collect();
+        let line: Vec<String> = points
+            .iter()
+            .map(|(point, _)| {
+                let (px, py) = to_px(*point);
+                format!("{px:.1},{py:.1}")
+            })
+            .collect();
+        writeln!(
+            page,
+            "<polyline fill=\"none\" stroke=\"{color}\" stroke-width=\"2\" stroke-dasharray=\"{}\" points=\"{}\"/>",
+            s.style.dash.svg(),
+            line.join(" ")
+        )?;
+        for ((x, y), name) in points {
+            let tooltip = format!(
+                "{}\n{}\n{}: {}\n{}: {}",
+                s.label, name, chart.x.desc, x, chart.y.desc, y
+            );
+            let (px, py) = to_px((x, y));
+            write_marker(page, s.style.marker, (px, py), 4.0, &color, Some(&tooltip))?;
+            writeln!(page)?;
+        }
+    }
+    for ((x, y), text) in chart.annotations.iter() {
+        if !visible((*x, *y)) {
+            continue;
+        }
+        let (px, py) = to_px((*x, *y));
+        writeln!(
+            page,
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"13\">{}</text>",
+            px + 8.0,
+            py - 8.0,
+            escape(text)
+        )?;
+    }
+    writeln!(page, "</svg>")?;
+
+    write_legend(page, &chart.series)?;
+    Ok(())
+}
+
+fn write_legend(page: &mut String, series: &[Series]) -> anyhow::Result<()> {
+    writeln!(page, "<div class=\"legend\">")?;
+    for s in series {
+        let color = hex(s.style.color);
+        write!(page, "<span><svg width=\"16\" height=\"16\">")?;
+        write_marker(page, s.style.marker, (8.0, 8.0), 6.0, &color, None)?;
+        writeln!(page, "</svg>{}</span>", escape(s.label))?;
+    }
+    writeln!(page, "</div>")?;
+    Ok(())
+}
+
+/// Series without a marker get circles, the tooltip shows up when hovering the marker
+fn write_marker(
+    page: &mut String,
+    marker: Option<Marker>,
+    (x, y): (f64, f64),
+    size: f64,
+    color: &str,
+    tooltip: Option<&str>,
+) -> anyhow::Result<()> {
+    let style = format!("class=\"point\" fill=\"white\" stroke=\"{color}\" stroke-width=\"2\"");
+    let title = tooltip
+        .map(|tooltip| format!("<title>{}</title>", escape(tooltip)))
+        .unwrap_or_default();
+    match marker.unwrap_or(Marker::Circle) {
+        Marker::Circle => write!(
+            page,
+            "<circle {style} cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{size}\">{title}</circle>"
+        )?,
+        Marker::Square => write!(
+            page,
+            "<rect {style} x=\"{:.1}\" y=\"{:.1}\" width=\"{}\" height=\"{}\">{title}</rect>",
+            x - size,
+            y - size,
+            2.0 * size,
+            2.0 * size
+        )?,
+        Marker::Triangle => write!(
+            page,
+            "<polygon {style} points=\"{x:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\">{title}</polygon>",
+            y - size,
+            x + size,
+            y + size,
+            x - size,
+            y + size
+        )?,
+        Marker::Cross => write!(
+            page,
+            "<path {style} d=\"M{:.1},{y:.1}H{:.1}M{x:.1},{:.1}V{:.1}\">{title}</path>",
+            x - size,
+            x + size,
+            y - size,
+            y + size
+        )?,
+    }
+    Ok(())
+}
+
+/// Position of `value` within the range, from 0 to 1
+fn fraction(value: f64, range: &Range<f64>, log: bool) -> f64 {
+    if log {
+        (value.ln() - range.start.ln()) / (range.end.ln() - range.start.ln())
+    } else {
+        (value - range.start) / (range.end - range.start)
+    }
+}
+
+/// Powers of ten on log axes, steps of 1, 2 or 5 times a power of ten on linear axes
+fn ticks(range: &Range<f64>, log: bool) -> Vec<f64> {
+    if log {
+        let first = range.start.log10().ceil() as i32;
+        let last = range.end.log10().floor() as i32;
+        let mut ticks: Vec<f64> = (first..=last).map(|exp| 10f64.powi(exp)).collect();
+        // narrow ranges would not get any label otherwise
+        if ticks.len() < 2 {
+            ticks = (first - 1..=last)
+                .flat_map(|exp| [1.0, 2.0, 5.0].map(|m| m * 10f64.powi(exp)))
+                .filter(|tick| range.contains(tick))
+                .collect();
+        }
+        ticks
+    } else {
+        let step = linear_step(range);
+        let first = (range.start / step).ceil() as i64;
+        let last = (range.end / step).floor() as i64;
+        (first..=last).map(|i| i as f64 * step).collect()
+    }
+}
+
+fn linear_step(range: &Range<f64>) -> f64 {
+    let rough = (range.end - range.start) / 8.0;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude)
+}
+
+fn tick_label(tick: f64, range: &Range<f64>, log: bool) -> String {
+    let smallest = if log { tick } else { linear_step(range) };
+    let decimals = (-smallest.log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, tick)
+}
+
+fn hex(color: RGBColor) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
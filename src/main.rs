@@ -1,129 +1,314 @@
-use std::io::{Read, Write};
+use std::path::Path;
 
 use bencher::Bencher;
-use bfunc::Bfunc;
-use utils::decompress_default;
+use clap::Parser;
+use cli::{
+    BenchArgs, ChartArgs, Cli, Command, CompareArgs, DictSweepArgs, DiffArgs, InspectArgs,
+    RenderArgs, TrainDictArgs,
+};
+use report::BenchReport;
 
 mod bencher;
 mod bfunc;
+mod cli;
 mod comp;
+mod corpus;
+mod cpu;
 mod decomp;
+mod diff;
+mod export;
 mod graph;
+mod html;
+mod memory;
+mod render;
+mod report;
+mod spec;
+mod stats;
+mod sweep;
 mod utils;
 mod zstd_dict;
 mod zstd_impl;
 mod zstd_impl_debug;
 
-const COMP_1: &[u8] = include_bytes!("../files/1.rnote");
-const COMP_2: &[u8] = include_bytes!("../files/2.rnote");
-const COMP_3: &[u8] = include_bytes!("../files/3.rnote");
-const COMP_4: &[u8] = include_bytes!("../files/4.rnote");
-const COMP_5: &[u8] = include_bytes!("../files/5.rnote");
-const COMP_6: &[u8] = include_bytes!("../files/6.rnote");
-const COMP_7: &[u8] = include_bytes!("../files/7.rnote");
-const COMP_8: &[u8] = include_bytes!("../files/8.rnote");
-const COMP_9: &[u8] = include_bytes!("../files/9.rnote");
-const COMP_10: &[u8] = include_bytes!("../files/10.rnote");
-const COMP_11: &[u8] = include_bytes!("../files/11.rnote");
-
-fn main() {
-    bench()
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Bench(args) => bench(args),
+        Command::Render(args) => render(args),
+        Command::Compare(args) => compare(args),
+        Command::Diff(args) => diff(args),
+        Command::TrainDict(args) => create_dict(args),
+        Command::DictSweep(args) => dict_sweep(args),
+        Command::Inspect(args) => inspect(args),
+    }
 }
 
-fn bench() {
-    #[cfg(debug_assertions)]
-    panic!("must be run in release mode");
-
-    let decomp_1 = decompress_default(COMP_1);
-    let decomp_2 = decompress_default(COMP_2);
-    let decomp_3 = decompress_default(COMP_3);
-    let decomp_4 = decompress_default(COMP_4);
-    let decomp_5 = decompress_default(COMP_5);
-    let decomp_6 = decompress_default(COMP_6);
-    let decomp_7 = decompress_default(COMP_7);
-    let decomp_8 = decompress_default(COMP_8);
-    let decomp_9 = decompress_default(COMP_9);
-    let decomp_10 = decompress_default(COMP_10);
-    let decomp_11 = decompress_default(COMP_11);
-
-    let nw = std::thread::available_parallelism().unwrap().get() as u32;
-
-    let gen_bencher = Bencher::new(
-        vec![
-            Bfunc::new("gzip-5-(current)", comp::gzip(5), decomp::gzip()),
-            Bfunc::new(
-                "brotli-2-4096-24",
-                comp::brotli(2, 4096, 24),
-                decomp::brotli(),
-            ),
-            Bfunc::new(
-                "brotli-4-4096-24",
-                comp::brotli(4, 4096, 24),
-                decomp::brotli(),
-            ),
-            Bfunc::new("par-gzip-9", comp::par_gzip(9), decomp::gzip()),
-            Bfunc::new("par-zstd-9", comp::par_zstd(9, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-9-opt", comp::par_zstd_opt(), decomp::zstd_opt()),
-        ],
-        vec![
-            &decomp_1, &decomp_2, &decomp_3, &decomp_4, &decomp_5, &decomp_6, &decomp_7, &decomp_8,
-            &decomp_9, &decomp_10, &decomp_11,
-        ],
+fn bench(args: BenchArgs) -> anyhow::Result<()> {
+    if cfg!(debug_assertions) {
+        anyhow::bail!("must be run in release mode");
+    }
+
+    let sampling = args.sampling()?;
+    let functions = args
+        .codecs
+        .iter()
+        .map(|spec| spec::parse(spec))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let corpus = corpus::load(&args.corpus.sources)?;
+    for input in corpus.iter() {
+        println!("loaded '{}' ({} bytes)", input.name, input.data.len());
+    }
+    check_held_out(&functions, &corpus)?;
+    std::fs::create_dir_all(&args.output_dir)?;
+    let report = Bencher::new(functions, &corpus).measure(sampling, args.warmup, args.warmup_once);
+
+    println!("\nmeasured on {}", report.environment);
+
+    let stem = report.file_stem();
+    report.save(&args.output_dir.join(format!("{}.report.json", stem)))?;
+    report.save_metadata(&args.output_dir.join(format!("{}.meta.json", stem)))?;
+    draw_charts(&report, &args.output_dir, &stem, &args.chart)?;
+    export::write_csv(&args.output_dir.join(format!("{}.csv", stem)), &report)?;
+    export::write_json(&args.output_dir.join(format!("{}.json", stem)), &report)?;
+    report.print_failures();
+    Ok(())
+}
+
+fn render(args: RenderArgs) -> anyhow::Result<()> {
+    let report = BenchReport::load(&args.report)?;
+    println!("measured on {}", report.environment);
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    draw_charts(&report, &args.output_dir, &report.file_stem(), &args.chart)?;
+    Ok(())
+}
+
+fn compare(args: CompareArgs) -> anyhow::Result<()> {
+    let reports = args
+        .reports
+        .iter()
+        .map(|path| BenchReport::load(path))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    for (path, report) in args.reports.iter().zip(reports.iter()) {
+        println!("'{}' measured on {}", path.display(), report.environment);
+    }
+    if reports.len() > render::MAX_COMPARED {
+        eprintln!(
+            "warning: only {} reports can be told apart, the styles repeat past them",
+            render::MAX_COMPARED
+        );
+    }
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    for format in args.chart.formats.iter() {
+        for path in render::compare(
+            &reports,
+            &args.output_dir,
+            *format,
+            args.chart.scales(),
+            args.chart.palette,
+        )? {
+            println!("wrote '{}'", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn diff(args: DiffArgs) -> anyhow::Result<()> {
+    let baseline = BenchReport::load(&args.baseline)?;
+    let new = BenchReport::load(&args.new)?;
+    println!("baseline measured on {}", baseline.environment);
+    println!("new run measured on {}", new.environment);
+    if baseline.environment.cpu != new.environment.cpu {
+        println!("warning: the runs were measured on different CPUs, times are not comparable");
+    }
+    println!();
+
+    let diff = diff::Diff::new(&baseline, &new, args.thresholds());
+    diff.print();
+
+    let num_of_regressions = diff.num_of_regressions();
+    if num_of_regressions > 0 {
+        anyhow::bail!(
+            "{} of {} (codec, input) pairs regressed",
+            num_of_regressions,
+            diff.num_of_pairs()
+        );
+    }
+    Ok(())
+}
+
+fn draw_charts(
+    report: &BenchReport,
+    output_dir: &Path,
+    stem: &str,
+    args: &ChartArgs,
+) -> anyhow::Result<()> {
+    for format in args.formats.iter() {
+        let path = output_dir.join(format!("{}.{}", stem, format.extension()));
+        render::chart(report, &path, *format, args.scales(), args.palette)?;
+        println!("wrote '{}'", path.display());
+
+        let path = output_dir.join(format!("{}_pareto.{}", stem, format.extension()));
+        render::pareto(report, &path, *format, args.scales(), args.palette)?;
+        println!("wrote '{}'", path.display());
+    }
+    for codec in report.codecs.iter() {
+        if report.has_failures(&codec.title) {
+            eprintln!(
+                "'{}' is left out of the Pareto chart, it failed on some inputs",
+                codec.title
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Trained codecs must not be measured on their training inputs, their ratio would be flattered
+fn check_held_out(
+    functions: &[bfunc::Bfunc<comp::CompFunc, decomp::DecompFunc>],
+    corpus: &[corpus::Input],
+) -> anyhow::Result<()> {
+    for function in functions {
+        for input in corpus {
+            if function
+                .training_set
+                .contains(&crc32fast::hash(&input.data))
+            {
+                anyhow::bail!(
+                    "'{}' was trained on '{}', benchmark it on a held-out corpus (--corpus)",
+                    function.title,
+                    input.name
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn create_dict(args: TrainDictArgs) -> anyhow::Result<()> {
+    let params = args.train.params(args.max_size);
+    let samples = zstd_dict::collect(&args.corpus, &params)?;
+    println!(
+        "training on {} samples ({} bytes)",
+        samples.len(),
+        samples.total_size()
+    );
+    let (dict, training_set) = zstd_dict::train(&samples, &params)?;
+    println!(
+        "dictionary {} ({} bytes, k={}, d={})",
+        training_set.dict_id,
+        dict.len(),
+        training_set.params.k,
+        training_set.params.d
     );
+    zstd_dict::save(&args.output, &dict, &training_set)?;
+    println!(
+        "wrote '{}' and '{}'",
+        args.output.display(),
+        zstd_dict::sidecar_path(&args.output).display()
+    );
+    Ok(())
+}
 
-    gen_bencher.run(16);
+/// Trains a dictionary of every size on the same samples and measures them next to zstd
+/// without a dictionary at the same level
+fn dict_sweep(mut args: DictSweepArgs) -> anyhow::Result<()> {
+    if cfg!(debug_assertions) {
+        anyhow::bail!("must be run in release mode");
+    }
+    // the chart only draws lines towards larger sizes
+    args.sizes.sort();
+    args.sizes.dedup();
 
-    let bencher = Bencher::new(
-        vec![
-            Bfunc::new("par-zstd-3", comp::par_zstd(5, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-4", comp::par_zstd(5, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-5", comp::par_zstd(5, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-6", comp::par_zstd(6, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-7", comp::par_zstd(7, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-8", comp::par_zstd(8, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-9", comp::par_zstd(9, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-10", comp::par_zstd(10, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-11", comp::par_zstd(11, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-12", comp::par_zstd(12, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-13", comp::par_zstd(13, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-14", comp::par_zstd(14, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-15", comp::par_zstd(15, nw), decomp::zstd()),
-            Bfunc::new("par-zstd-16", comp::par_zstd(16, nw), decomp::zstd()),
-        ],
+    let level = args.train.level;
+    let samples = zstd_dict::collect(&args.training, &args.train.params(0))?;
+    println!(
+        "training on {} samples ({} bytes)",
+        samples.len(),
+        samples.total_size()
+    );
+    let mut functions =
         vec![
-            &decomp_1, &decomp_2, &decomp_3, &decomp_4, &decomp_5, &decomp_6, &decomp_7, &decomp_8,
-            &decomp_9, &decomp_10, &decomp_11,
-        ],
+            bfunc::Bfunc::new(format!("zstd-{}", level), comp::zstd(level), decomp::zstd())
+                .allocates_in_c(true),
+        ];
+    for size in args.sizes.iter() {
+        let (dict, training_set) = zstd_dict::train(&samples, &args.train.params(*size))?;
+        println!(
+            "trained a {} dictionary ({} bytes, k={}, d={})",
+            sweep::format_size(*size),
+            dict.len(),
+            training_set.params.k,
+            training_set.params.d
+        );
+        functions.push(
+            bfunc::Bfunc::new(
+                format!("zstd-dict-{} ({})", level, sweep::format_size(*size)),
+                comp::zstd_dict(level, &dict),
+                decomp::zstd_dict(&dict),
+            )
+            .trained_on(training_set.hashes())
+            .allocates_in_c(true),
+        );
+    }
+
+    let corpus = corpus::load(&args.corpus.sources)?;
+    for input in corpus.iter() {
+        println!("loaded '{}' ({} bytes)", input.name, input.data.len());
+    }
+    check_held_out(&functions, &corpus)?;
+    std::fs::create_dir_all(&args.output_dir)?;
+    let report = Bencher::new(functions, &corpus).measure(
+        bfunc::Sampling::Fixed(args.samples),
+        args.warmup,
+        false,
     );
+    println!("\nmeasured on {}\n", report.environment);
+    report.print_failures();
+
+    let (baseline, dicts) = report
+        .codecs
+        .split_first()
+        .ok_or(anyhow::anyhow!("nothing was measured"))?;
+    let dicts: Vec<(usize, &report::CodecReport)> =
+        args.sizes.iter().copied().zip(dicts.iter()).collect();
+    let sweep = sweep::Sweep::new(baseline, &dicts);
+    sweep.print();
 
-    bencher.run(6);
+    let stem = report.file_stem();
+    report.save(&args.output_dir.join(format!("{}.report.json", stem)))?;
+    for format in args.chart.formats.iter() {
+        let path = args
+            .output_dir
+            .join(format!("{}_dict_sweep.{}", stem, format.extension()));
+        render::dict_sweep(
+            &sweep,
+            &report.environment,
+            &path,
+            *format,
+            args.chart.scales(),
+            args.chart.palette,
+        )?;
+        println!("wrote '{}'", path.display());
+    }
+    Ok(())
 }
 
-/*
-Bfunc::new("gzip-5", comp::gzip(5), decomp::gzip()),
-Bfunc::new(
-    "brotli-2-4096-24",
-    comp::brotli(2, 4096, 24),
-    decomp::brotli(),
-),
-Bfunc::new(
-    "brotli-4-4096-24",
-    comp::brotli(4, 4096, 24),
-    decomp::brotli(),
-),
-Bfunc::new("par-gzip-9", comp::par_gzip(9), decomp::gzip()),
-Bfunc::new("par-zstd-9", comp::par_zstd(9, nw), decomp::zstd()),
-Bfunc::new("par-zstd-9-opt", comp::par_zstd_opt(), decomp::zstd_opt()),
-*/
-
-fn create_dict() {
-    let dict = crate::zstd_dict::train(None, "./files/dict/".as_ref());
-    std::fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open("rnote.dict")
-        .unwrap()
-        .write_all(&dict)
-        .unwrap();
+fn inspect(args: InspectArgs) -> anyhow::Result<()> {
+    let corpus = corpus::load(&args.corpus.sources)?;
+    let total: usize = corpus.iter().map(|input| input.data.len()).sum();
+    for input in corpus.iter() {
+        println!("{:<24} {:>12} bytes", input.name, input.data.len());
+    }
+    println!(
+        "{} files, {:.2} MB decompressed in total",
+        corpus.len(),
+        total as f64 / 1e6
+    );
+
+    if args.zstd_frames {
+        crate::zstd_impl_debug::test(&corpus);
+    }
+    Ok(())
 }
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering},
+};
+
+// only the allocations going through the rust allocator are counted, the buffers that C libraries
+// (zstd, lz4, liblzma) allocate with malloc themselves are not visible here,
+// the peak is taken from the resident memory of the process instead where it can be read
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+// only set while `measure` runs, the timed runs only pay for reading it
+static COUNTING: AtomicBool = AtomicBool::new(false);
+// relative to the start of the measurement, freeing older memory makes it negative
+static CURRENT: AtomicIsize = AtomicIsize::new(0);
+static PEAK: AtomicIsize = AtomicIsize::new(0);
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, keeping track of the bytes in use, their peak and the total allocated
+/// while a measurement runs
+struct CountingAlloc;
+
+impl CountingAlloc {
+    fn grow(size: usize) {
+        if !COUNTING.load(Ordering::Relaxed) {
+            return;
+        }
+        let current = CURRENT.fetch_add(size as isize, Ordering::Relaxed) + size as isize;
+        PEAK.fetch_max(current, Ordering::Relaxed);
+        TOTAL.fetch_add(size, Ordering::Relaxed);
+    }
+
+    fn shrink(size: usize) {
+        if !COUNTING.load(Ordering::Relaxed) {
+            return;
+        }
+        CURRENT.fetch_sub(size as isize, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                Self::grow(new_size - layout.size());
+            } else {
+                Self::shrink(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Memory usage of a single run, in bytes
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct MemUsage {
+    /// highest amount of memory in use at once, on top of what was in use before the run,
+    /// `None` when it could not be measured
+    pub peak: Option<usize>,
+    /// sum of every allocation, including memory that was freed during the run,
+    /// `None` for codecs that allocate in C
+    pub total: Option<usize>,
+}
+
+/// Runs `f` and measures its memory usage, the counters are process wide so nothing else should run at the same time
+///
+/// on Linux the peak is the growth of the resident memory of the process, which covers the allocations
+/// made by C libraries, elsewhere it is the peak of the counted heap, unknown for codecs that allocate in C
+pub fn measure<T>(f: impl FnOnce() -> T, allocates_in_c: bool) -> (T, MemUsage) {
+    let rss_before = rss::reset_peak();
+    CURRENT.store(0, Ordering::SeqCst);
+    PEAK.store(0, Ordering::SeqCst);
+    TOTAL.store(0, Ordering::SeqCst);
+    COUNTING.store(true, Ordering::SeqCst);
+
+    let output = f();
+
+    COUNTING.store(false, Ordering::SeqCst);
+    let rss_peak = rss_before.and_then(|before| Some(rss::peak()?.saturating_sub(before)));
+    let heap_peak = PEAK.load(Ordering::SeqCst).max(0) as usize;
+    let usage = MemUsage {
+        peak: rss_peak.or((!allocates_in_c).then_some(heap_peak)),
+        total: (!allocates_in_c).then(|| TOTAL.load(Ordering::SeqCst)),
+    };
+    (output, usage)
+}
+
+#[cfg(target_os = "linux")]
+mod rss {
+    /// Resets the peak resident memory of the process to the current one, returns the latter
+    pub fn reset_peak() -> Option<usize> {
+        // hands the memory freed by earlier runs back to the kernel, reusing it would not show up otherwise
+        #[cfg(target_env = "gnu")]
+        // SAFETY: only releases free memory of the malloc arenas
+        unsafe {
+            libc::malloc_trim(0);
+        }
+        std::fs::write("/proc/self/clear_refs", "5").ok()?;
+        status("VmRSS:")
+    }
+
+    /// Highest resident memory since the last reset
+    pub fn peak() -> Option<usize> {
+        status("VmHWM:")
+    }
+
+    // e.g. `VmHWM:\t    1780 kB`
+    fn status(key: &str) -> Option<usize> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let value = status.lines().find_map(|line| line.strip_prefix(key))?;
+        let kb: usize = value.trim().strip_suffix("kB")?.trim().parse().ok()?;
+        Some(kb * 1024)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod rss {
+    pub fn reset_peak() -> Option<usize> {
+        None
+    }
+
+    pub fn peak() -> Option<usize> {
+        None
+    }
+}
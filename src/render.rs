@@ -0,0 +1,593 @@
+use crate::{
+    bfunc::BenchResult,
+    graph::{
+        draw_error, draw_panel, Axis, Chart, Dash, Marker, Palette, Quantity, Scales, Series,
+        Style, DASHES, MARKERS, REFERENCE,
+    },
+    html,
+    report::{BenchReport, CodecReport, Environment},
+    sweep::{Gain, Sweep, BUCKETS},
+};
+use anyhow::Context;
+use plotters::{coord::Shift, prelude::*};
+use std::path::{Path, PathBuf};
+
+/// Output format of the charts
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ChartFormat {
+    Png,
+    Svg,
+    /// a single self-contained page, hovering a point shows its exact values
+    Html,
+}
+
+impl ChartFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ChartFormat::Png => "png",
+            ChartFormat::Svg => "svg",
+            ChartFormat::Html => "html",
+        }
+    }
+}
+
+/// Draws every panel of the report into a single image, one panel under the other
+pub fn chart(
+    report: &BenchReport,
+    path: &Path,
+    format: ChartFormat,
+    scales: Scales,
+    palette: Palette,
+) -> anyhow::Result<()> {
+    let charts: Vec<Chart> = panels()
+        .into_iter()
+        .map(|(caption, x, y, point)| Chart {
+            caption,
+            x,
+            y,
+            series: report
+                .codecs
+                .iter()
+                .enumerate()
+                .map(|(idx, codec)| series(&codec.title, palette.style(idx), &codec.results, point))
+                .collect(),
+            annotations: Vec::new(),
+            legend: SeriesLabelPosition::UpperLeft,
+        })
+        .collect();
+
+    write(
+        &charts,
+        path,
+        format,
+        (&report.file_stem(), &[report.environment.to_string()]),
+        (1200, 600),
+        scales,
+    )
+}
+
+/// Draws the compression ratio over all inputs against the aggregate compression and
+/// decompression throughputs of every codec, the Pareto frontier links the codecs that
+/// no other codec beats on both
+///
+/// codecs that failed on some inputs are left out, their totals are not comparable
+pub fn pareto(
+    report: &BenchReport,
+    path: &Path,
+    format: ChartFormat,
+    scales: Scales,
+    palette: Palette,
+) -> anyhow::Result<()> {
+    const RATIO: Axis = Axis {
+        desc: "compression ratio (all inputs)",
+        quantity: Quantity::Ratio,
+    };
+    let codecs: Vec<(usize, &CodecReport)> = report
+        .codecs
+        .iter()
+        .enumerate()
+        .filter(|(_, codec)| !codec.results.is_empty() && !report.has_failures(&codec.title))
+        .collect();
+
+    let charts: Vec<Chart> = [
+        (
+            "Pareto frontier, compression",
+            Axis {
+                desc: "compression throughput (all inputs) [MB/s]",
+                quantity: Quantity::Throughput,
+            },
+            CodecReport::total_comp_throughput as fn(&CodecReport) -> f64,
+        ),
+        (
+            "Pareto frontier, decompression",
+            Axis {
+                desc: "decompression throughput (all inputs) [MB/s]",
+                quantity: Quantity::Throughput,
+            },
+            CodecReport::total_decomp_throughput,
+        ),
+    ]
+    .into_iter()
+    .map(|(caption, x, throughput)| {
+        let points: Vec<(f64, f64)> = codecs
+            .iter()
+            .map(|(_, codec)| (throughput(codec), codec.total_ratio()))
+            .collect();
+        let optimal = pareto_optimal(&points);
+
+        let mut frontier: Vec<(f64, f64)> = points
+            .iter()
+            .zip(optimal.iter())
+            .filter_map(|(point, optimal)| optimal.then_some(*point))
+            .collect();
+        frontier.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut series: Vec<Series> = codecs
+            .iter()
+            .zip(points.iter())
+            .map(|((idx, codec), point)| Series {
+                label: &codec.title,
+                style: Style {
+                    marker: Some(Marker::Circle),
+                    ..palette.style(*idx)
+                },
+                points: vec![*point],
+                names: vec!["all inputs"],
+            })
+            .collect();
+        series.push(Series {
+            label: "Pareto frontier",
+            style: Style {
+                color: REFERENCE,
+                marker: None,
+                dash: Dash::Solid,
+            },
+            names: vec!["all inputs"; frontier.len()],
+            points: frontier,
+        });
+
+        Chart {
+            caption,
+            x,
+            y: RATIO,
+            series,
+            annotations: codecs
+                .iter()
+                .zip(points.iter().zip(optimal.iter()))
+                .filter(|(_, (_, optimal))| **optimal)
+                .map(|((_, codec), (point, _))| (*point, codec.title.clone()))
+                .collect(),
+            // the frontier runs from the upper left to the lower right, codecs that are both slow
+            // and weak are rare
+            legend: SeriesLabelPosition::LowerLeft,
+        }
+    })
+    .collect();
+
+    write(
+        &charts,
+        path,
+        format,
+        (&report.file_stem(), &[report.environment.to_string()]),
+        (1200, 800),
+        scales,
+    )
+}
+
+/// Draws the gains of the dictionaries against their size, one series per note size bucket
+pub fn dict_sweep(
+    sweep: &Sweep,
+    environment: &Environment,
+    path: &Path,
+    format: ChartFormat,
+    scales: Scales,
+    palette: Palette,
+) -> anyhow::Result<()> {
+    const DICT_SIZE: Axis = Axis {
+        desc: "dictionary size [KiB]",
+        quantity: Quantity::Size,
+    };
+    let names: Vec<Vec<String>> = BUCKETS
+        .iter()
+        .map(|(bucket, _)| {
+            sweep
+                .gains
+                .iter()
+                .filter(|gain| gain.bucket == *bucket)
+                .map(|gain| format!("{} notes", gain.num_of_notes))
+                .collect()
+        })
+        .collect();
+    let sizes = || {
+        sweep
+            .gains
+            .iter()
+            .map(|gain| gain.dict_size as f64 / 1024.0)
+    };
+    // drawn at zero, above it the dictionary helps
+    let no_gain = [
+        sizes().fold(f64::INFINITY, f64::min),
+        sizes().fold(f64::NEG_INFINITY, f64::max),
+    ];
+
+    let charts: Vec<Chart> = [
+        (
+            "Compression ratio gain",
+            "ratio gain [%]",
+            (|gain| gain.ratio) as fn(&Gain) -> f64,
+        ),
+        (
+            "Compression throughput gain",
+            "compression throughput gain [%]",
+            |gain| gain.comp_throughput,
+        ),
+        (
+            "Decompression throughput gain",
+            "decompression throughput gain [%]",
+            |gain| gain.decomp_throughput,
+        ),
+    ]
+    .into_iter()
+    .map(|(caption, desc, value)| {
+        let mut series: Vec<Series> = BUCKETS
+            .iter()
+            .zip(names.iter())
+            .enumerate()
+            .filter(|(_, (_, names))| !names.is_empty())
+            .map(|(idx, ((bucket, _), names))| Series {
+                label: bucket,
+                style: Style {
+                    marker: Some(Marker::Circle),
+                    ..palette.style(idx)
+                },
+                points: sweep
+                    .gains
+                    .iter()
+                    .filter(|gain| gain.bucket == *bucket)
+                    .map(|gain| (gain.dict_size as f64 / 1024.0, value(gain) * 100.0))
+                    .collect(),
+                names: names.iter().map(String::as_str).collect(),
+            })
+            .collect();
+        series.push(Series {
+            label: "no gain",
+            style: Style {
+                color: REFERENCE,
+                marker: None,
+                dash: Dash::Dotted,
+            },
+            points: no_gain.iter().map(|size| (*size, 0.0)).collect(),
+            names: vec!["zstd without a dictionary"; 2],
+        });
+        Chart {
+            caption,
+            x: DICT_SIZE,
+            y: Axis {
+                desc,
+                quantity: Quantity::Gain,
+            },
+            series,
+            annotations: Vec::new(),
+            legend: SeriesLabelPosition::UpperRight,
+        }
+    })
+    .collect();
+
+    write(
+        &charts,
+        path,
+        format,
+        ("dictionary size sweep", &[environment.to_string()]),
+        (1200, 600),
+        // the sizes grow geometrically
+        Scales {
+            log_size: true,
+            ..scales
+        },
+    )
+}
+
+/// Whether each point is Pareto-optimal, i.e. no other point is at least as high on both axes
+/// and higher on one
+fn pareto_optimal(points: &[(f64, f64)]) -> Vec<bool> {
+    points
+        .iter()
+        .map(|a| {
+            !points
+                .iter()
+                .any(|b| b.0 >= a.0 && b.1 >= a.1 && (b.0 > a.0 || b.1 > a.1))
+        })
+        .collect()
+}
+
+/// Writes the charts one under the other, `size` is the size of a single chart
+fn write(
+    charts: &[Chart],
+    path: &Path,
+    format: ChartFormat,
+    (title, environments): (&str, &[String]),
+    size: (u32, u32),
+    scales: Scales,
+) -> anyhow::Result<()> {
+    let size = (size.0, size.1 * charts.len() as u32);
+    match format {
+        ChartFormat::Png => draw(
+            &BitMapBackend::new(path, size).into_drawing_area(),
+            charts,
+            scales,
+        )
+        .with_context(|| format!("failed to write '{}'", path.display()))?,
+        ChartFormat::Svg => draw(
+            &SVGBackend::new(path, size).into_drawing_area(),
+            charts,
+            scales,
+        )
+        .with_context(|| format!("failed to write '{}'", path.display()))?,
+        ChartFormat::Html => html::write(path, title, environments, charts, scales)?,
+    }
+    Ok(())
+}
+
+fn draw<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    charts: &[Chart],
+    scales: Scales,
+) -> anyhow::Result<()> {
+    root.fill(&WHITE).map_err(draw_error)?;
+
+    for (chart, area) in charts
+        .iter()
+        .zip(root.split_evenly((charts.len(), 1)).iter())
+    {
+        draw_panel(area, chart, scales)?;
+    }
+
+    root.present().map_err(draw_error)
+}
+
+/// Reports that `compare` can tell apart, each gets its own marker and dash pattern
+pub const MAX_COMPARED: usize = MARKERS.len() * DASHES.len();
+
+/// Draws one chart per panel comparing several reports, a codec keeps its colour across reports
+/// and every report gets its own marker, then its own dash pattern, returns the paths of the charts
+///
+/// HTML puts every chart on the same page
+pub fn compare(
+    reports: &[BenchReport],
+    output_dir: &Path,
+    format: ChartFormat,
+    scales: Scales,
+    palette: Palette,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut codecs: Vec<&str> = Vec::new();
+    for report in reports {
+        for codec in report.codecs.iter() {
+            if !codecs.contains(&codec.title.as_str()) {
+                codecs.push(&codec.title);
+            }
+        }
+    }
+
+    // the file stem tells apart runs on the same CPU
+    let labels: Vec<Vec<String>> = reports
+        .iter()
+        .map(|report| {
+            report
+                .codecs
+                .iter()
+                .map(|codec| {
+                    format!(
+                        "{} ({}, {})",
+                        codec.title,
+                        report.environment.cpu,
+                        report.file_stem()
+                    )
+                })
+                .collect()
+        })
+        .collect();
+
+    let charts: Vec<Chart> = panels()
+        .into_iter()
+        .map(|(caption, x, y, point)| Chart {
+            caption,
+            x,
+            y,
+            series: reports
+                .iter()
+                .zip(labels.iter())
+                .enumerate()
+                .flat_map(|(report_idx, (report, labels))| {
+                    let codecs = &codecs;
+                    report
+                        .codecs
+                        .iter()
+                        .zip(labels.iter())
+                        .map(move |(codec, label)| {
+                            let codec_idx = codecs.iter().position(|c| *c == codec.title).unwrap();
+                            let style = Style {
+                                // a dash pattern once the markers run out, repeating past `MAX_COMPARED` reports
+                                marker: Some(MARKERS[report_idx % MARKERS.len()]),
+                                dash: DASHES[report_idx / MARKERS.len() % DASHES.len()],
+                                ..palette.style(codec_idx)
+                            };
+                            series(label, style, &codec.results, point)
+                        })
+                })
+                .collect(),
+            annotations: Vec::new(),
+            legend: SeriesLabelPosition::UpperLeft,
+        })
+        .collect();
+
+    let environments: Vec<String> = reports
+        .iter()
+        .map(|report| report.environment.to_string())
+        .collect();
+    if let ChartFormat::Html = format {
+        let path = output_dir.join("compare.html");
+        write(
+            &charts,
+            &path,
+            format,
+            ("comparison", &environments),
+            (1200, 800),
+            scales,
+        )?;
+        return Ok(vec![path]);
+    }
+
+    charts
+        .iter()
+        .map(|chart| {
+            let path = output_dir.join(format!("compare_{}.{}", chart.caption, format.extension()));
+            write(
+                std::slice::from_ref(chart),
+                &path,
+                format,
+                ("comparison", &environments),
+                (1200, 800),
+                scales,
+            )?;
+            Ok(path)
+        })
+        .collect()
+}
+
+fn series<'a>(
+    label: &'a str,
+    style: Style,
+    results: &'a [BenchResult],
+    point: fn(&BenchResult) -> Option<(f64, f64)>,
+) -> Series<'a> {
+    let (points, names) = results
+        .iter()
+        .filter_map(|result| point(result).map(|p| (p, result.input_name.as_str())))
+        .unzip();
+    Series {
+        label,
+        style,
+        points,
+        names,
+    }
+}
+
+/// Caption, axes and how a result is turned into a point of a chart
+type Panel = (
+    &'static str,
+    Axis<'static>,
+    Axis<'static>,
+    fn(&BenchResult) -> Option<(f64, f64)>,
+);
+
+fn panels() -> Vec<Panel> {
+    const DECOMP_SIZE: Axis = Axis {
+        desc: "decompressed size [MB]",
+        quantity: Quantity::Size,
+    };
+    vec![
+        (
+            "DS-CS",
+            DECOMP_SIZE,
+            Axis {
+                desc: "compressed size [MB]",
+                quantity: Quantity::Size,
+            },
+            |r| Some(r.decomp_size_comp_size()),
+        ),
+        (
+            "DS-CT",
+            DECOMP_SIZE,
+            Axis {
+                desc: "compression time [s]",
+                quantity: Quantity::Time,
+            },
+            |r| Some(r.decomp_size_comp_time()),
+        ),
+        (
+            "DS-CC",
+            DECOMP_SIZE,
+            Axis {
+                desc: "compression CPU time [s]",
+                quantity: Quantity::Time,
+            },
+            BenchResult::decomp_size_comp_cpu_time,
+        ),
+        (
+            "CS-DT",
+            Axis {
+                desc: "compressed size [MB]",
+                quantity: Quantity::Size,
+            },
+            Axis {
+                desc: "decompression time [s]",
+                quantity: Quantity::Time,
+            },
+            |r| Some(r.comp_size_decomp_time()),
+        ),
+        (
+            "DS-R",
+            DECOMP_SIZE,
+            Axis {
+                desc: "compression ratio",
+                quantity: Quantity::Ratio,
+            },
+            |r| Some(r.decomp_size_ratio()),
+        ),
+        (
+            "DS-CTP",
+            DECOMP_SIZE,
+            Axis {
+                desc: "compression throughput [MB/s]",
+                quantity: Quantity::Throughput,
+            },
+            |r| Some(r.decomp_size_comp_throughput()),
+        ),
+        (
+            "DS-DTP",
+            DECOMP_SIZE,
+            Axis {
+                desc: "decompression throughput [MB/s]",
+                quantity: Quantity::Throughput,
+            },
+            |r| Some(r.decomp_size_decomp_throughput()),
+        ),
+        (
+            "DS-CM",
+            DECOMP_SIZE,
+            Axis {
+                desc: "peak memory usage when compressing [MB]",
+                quantity: Quantity::Size,
+            },
+            BenchResult::decomp_size_comp_memory,
+        ),
+        (
+            "DS-DM",
+            DECOMP_SIZE,
+            Axis {
+                desc: "peak memory usage when decompressing [MB]",
+                quantity: Quantity::Size,
+            },
+            BenchResult::decomp_size_decomp_memory,
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pareto_optimal_points() {
+        let points = [(1.0, 3.0), (2.0, 2.0), (3.0, 1.0), (1.5, 1.5), (2.0, 1.0)];
+        assert_eq!(pareto_optimal(&points), [true, true, true, false, false]);
+    }
+
+    #[test]
+    fn pareto_optimal_ties() {
+        // equal points do not dominate each other
+        assert_eq!(pareto_optimal(&[(1.0, 1.0), (1.0, 1.0)]), [true, true]);
+        // better on one axis and equal on the other dominates
+        assert_eq!(pareto_optimal(&[(1.0, 1.0), (1.0, 2.0)]), [false, true]);
+    }
+}
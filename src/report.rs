@@ -0,0 +1,263 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::bfunc::BenchResult;
+
+/// Version of the saved report format, bumped on every incompatible change
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Everything measured during a run
+#[derive(Serialize, Deserialize)]
+pub struct BenchReport {
+    pub environment: Environment,
+    pub codecs: Vec<CodecReport>,
+    pub failures: Vec<Failure>,
+}
+
+impl BenchReport {
+    /// Every result, codec by codec
+    pub fn results(&self) -> impl Iterator<Item = &BenchResult> {
+        self.codecs.iter().flat_map(|codec| codec.results.iter())
+    }
+
+    /// Start of the run and a hash of the codec set, e.g. `20241018-104700_5e1f09a2`,
+    /// short and made of characters that are safe on every filesystem
+    pub fn file_stem(&self) -> String {
+        let (year, month, day, hour, minute, second) = utc(self.environment.timestamp);
+        format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}_{:08x}",
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            self.codec_set_hash()
+        )
+    }
+
+    /// CRC32 of the codec titles, in order
+    pub fn codec_set_hash(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        for codec in self.codecs.iter() {
+            hasher.update(codec.title.as_bytes());
+            hasher.update(b"\n");
+        }
+        hasher.finalize()
+    }
+
+    /// Writes what the file names no longer tell: the CPU, the codecs and the inputs of the run
+    pub fn save_metadata(&self, path: &Path) -> anyhow::Result<()> {
+        let mut inputs: Vec<&str> = Vec::new();
+        for result in self.results() {
+            if !inputs.contains(&result.input_name.as_str()) {
+                inputs.push(&result.input_name);
+            }
+        }
+        let metadata = Metadata {
+            run: self.file_stem(),
+            environment: &self.environment,
+            codecs: self
+                .codecs
+                .iter()
+                .map(|codec| codec.title.as_str())
+                .collect(),
+            codec_set_hash: format!("{:08x}", self.codec_set_hash()),
+            inputs,
+            num_of_failures: self.failures.len(),
+        };
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &metadata)?;
+        Ok(())
+    }
+
+    /// Writes the whole report as JSON, tagged with the format version
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("failed to create '{}'", path.display()))?;
+        serde_json::to_writer_pretty(
+            std::io::BufWriter::new(file),
+            &Saved {
+                version: FORMAT_VERSION,
+                report: self,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Reads a report written by [`BenchReport::save`], refusing other format versions
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        // the version is checked first so that old reports get a clear error instead of a missing field
+        let Version { version } = serde_json::from_str(&content)
+            .with_context(|| format!("'{}' is not a benchmark report", path.display()))?;
+        if version != FORMAT_VERSION {
+            anyhow::bail!(
+                "'{}' has report format version {}, only version {} is supported",
+                path.display(),
+                version,
+                FORMAT_VERSION
+            );
+        }
+        let Saved { report, .. }: Saved<Self> = serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse '{}'", path.display()))?;
+        Ok(report)
+    }
+
+    /// Whether the codec failed on any input, its totals would cover a smaller corpus than the others
+    pub fn has_failures(&self, title: &str) -> bool {
+        self.failures.iter().any(|failure| failure.title == title)
+    }
+
+    pub fn print_failures(&self) {
+        if self.failures.is_empty() {
+            return;
+        }
+        eprintln!(
+            "\n{} failure(s), skipped in the charts and exports:",
+            self.failures.len()
+        );
+        for failure in self.failures.iter() {
+            eprintln!(
+                "* '{}' on '{}': {}",
+                failure.title, failure.input_name, failure.error
+            );
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Saved<R> {
+    version: u32,
+    #[serde(flatten)]
+    report: R,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+/// Sidecar of the output files of a run
+#[derive(Serialize)]
+struct Metadata<'a> {
+    run: String,
+    environment: &'a Environment,
+    codecs: Vec<&'a str>,
+    codec_set_hash: String,
+    inputs: Vec<&'a str>,
+    num_of_failures: usize,
+}
+
+/// The results of one codec, in the order of the inputs
+#[derive(Serialize, Deserialize)]
+pub struct CodecReport {
+    pub title: String,
+    pub results: Vec<BenchResult>,
+}
+
+impl CodecReport {
+    /// ratio of the whole corpus, as if it was a single file
+    pub fn total_ratio(&self) -> f64 {
+        let uncompressed: usize = self.results.iter().map(|r| r.uncompressed_size).sum();
+        let compressed: usize = self.results.iter().map(|r| r.compressed_size).sum();
+        uncompressed as f64 / compressed as f64
+    }
+
+    /// uncompressed megabytes of the whole corpus over the sum of the median compression times
+    pub fn total_comp_throughput(&self) -> f64 {
+        self.total_uncompressed_mb() / self.results.iter().map(|r| r.comp_time.median).sum::<f64>()
+    }
+
+    /// uncompressed megabytes of the whole corpus over the sum of the median decompression times
+    pub fn total_decomp_throughput(&self) -> f64 {
+        self.total_uncompressed_mb()
+            / self
+                .results
+                .iter()
+                .map(|r| r.decomp_time.median)
+                .sum::<f64>()
+    }
+
+    fn total_uncompressed_mb(&self) -> f64 {
+        self.results
+            .iter()
+            .map(|r| r.uncompressed_size)
+            .sum::<usize>() as f64
+            / 1e6
+    }
+}
+
+/// A (codec, input) pair that could not be benchmarked
+#[derive(Serialize, Deserialize)]
+pub struct Failure {
+    pub title: String,
+    pub input_name: String,
+    pub error: String,
+}
+
+/// The machine the benchmark ran on
+#[derive(Serialize, Deserialize)]
+pub struct Environment {
+    pub cpu: String,
+    pub num_cores: usize,
+    pub os: String,
+    pub arch: String,
+    /// seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl Environment {
+    pub fn current() -> Self {
+        let system = sysinfo::System::new_with_specifics(
+            sysinfo::RefreshKind::new().with_cpu(sysinfo::CpuRefreshKind::new()),
+        );
+        Self {
+            cpu: system
+                .cpus()
+                .first()
+                .map(|cpu| cpu.brand().to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            num_cores: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl std::fmt::Display for Environment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day, hour, minute, second) = utc(self.timestamp);
+        write!(
+            f,
+            "{} ({} threads, {}/{}), started at {:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.cpu, self.num_cores, self.os, self.arch, year, month, day, hour, minute, second
+        )
+    }
+}
+
+/// Calendar date and time of a unix timestamp, avoids pulling in a date crate
+fn utc(timestamp: u64) -> (u64, u64, u64, u64, u64, u64) {
+    let (days, secs) = (timestamp / 86400, timestamp % 86400);
+    // civil date from days since the epoch, after Howard Hinnant's `civil_from_days`
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day, secs / 3600, secs % 3600 / 60, secs % 60)
+}
@@ -0,0 +1,227 @@
+use crate::{bfunc::Bfunc, comp, comp::CompFunc, decomp, decomp::DecompFunc, zstd_dict};
+use anyhow::Context;
+use std::path::Path;
+
+/// Parses a codec spec such as `zstd:9:mt=auto`, `brotli:4:buf=4096:win=24`, `gzip:5`, `par-gzip:9`
+/// or `zstd-dict:9:dict=rnote.dict`
+///
+/// specs are made of the codec name, an optional level and `key=value` options,
+/// `name=<title>` can be used with every codec to override the generated title
+pub fn parse(spec: &str) -> anyhow::Result<Bfunc<CompFunc, DecompFunc>> {
+    parse_inner(spec).with_context(|| format!("invalid codec spec '{}'", spec))
+}
+
+fn parse_inner(spec: &str) -> anyhow::Result<Bfunc<CompFunc, DecompFunc>> {
+    let mut parts = spec.split(':');
+    let codec = parts.next().unwrap_or_default();
+
+    let mut level: Option<&str> = None;
+    let mut options: Vec<(&str, &str)> = Vec::new();
+    for part in parts {
+        match part.split_once('=') {
+            Some(option) => options.push(option),
+            None if level.is_none() && options.is_empty() => level = Some(part),
+            None => anyhow::bail!(
+                "unexpected '{}', options must be written as key=value",
+                part
+            ),
+        }
+    }
+    let mut options = Options(options);
+    let name = options.take("name");
+    // inputs a dictionary was trained on
+    let mut hashes: Vec<u32> = Vec::new();
+
+    let (title, compressor, decompressor) = match codec {
+        "gzip" => {
+            let level: u32 = parse_level(level, 5)?;
+            if level > 9 {
+                anyhow::bail!("'gzip' levels go from 0 to 9");
+            }
+            (format!("gzip-{}", level), comp::gzip(level), decomp::gzip())
+        }
+        "par-gzip" => {
+            let level: u32 = parse_level(level, 5)?;
+            if level > 9 {
+                anyhow::bail!("'par-gzip' levels go from 0 to 9");
+            }
+            (
+                format!("par-gzip-{}", level),
+                comp::par_gzip(level),
+                decomp::gzip(),
+            )
+        }
+        "brotli" => {
+            let level: u32 = parse_level(level, 4)?;
+            let buffer_size: usize = options.parse("buf", 4096)?;
+            let window_size: u32 = options.parse("win", 24)?;
+            (
+                format!("brotli-{}-{}-{}", level, buffer_size, window_size),
+                comp::brotli(level, buffer_size, window_size),
+                decomp::brotli(),
+            )
+        }
+        "zstd" => {
+            let level: i32 = parse_level(level, 9)?;
+            match options.take("mt") {
+                None | Some("off") => {
+                    (format!("zstd-{}", level), comp::zstd(level), decomp::zstd())
+                }
+                Some(workers) => {
+                    let num_workers = parse_workers(workers)?;
+                    (
+                        format!("par-zstd-{}", level),
+                        comp::par_zstd(level, num_workers),
+                        decomp::zstd(),
+                    )
+                }
+            }
+        }
+        "zstd-dict" => {
+            let level: i32 = parse_level(level, 9)?;
+            let path = options.take("dict").ok_or(anyhow::anyhow!(
+                "'zstd-dict' needs a dictionary, e.g. dict=rnote.dict"
+            ))?;
+            let (dict, training_set) = zstd_dict::load(Path::new(path))?;
+            match training_set {
+                Some(training_set) => hashes = training_set.hashes(),
+                None => eprintln!(
+                    "warning: '{}' has no training set sidecar, make sure the corpus does not contain the notes it was trained on",
+                    path
+                ),
+            }
+            (
+                format!("zstd-dict-{}", level),
+                comp::zstd_dict(level, &dict),
+                decomp::zstd_dict(&dict),
+            )
+        }
+        "zstd-opt" => {
+            if level.is_some() {
+                anyhow::bail!("'zstd-opt' has a fixed level of 9");
+            }
+            (
+                "par-zstd-9-opt".to_string(),
+                comp::par_zstd_opt(),
+                decomp::zstd_opt(),
+            )
+        }
+        "lz4" => {
+            if level.is_some() {
+                anyhow::bail!("'lz4' has no level, use 'lz4-hc' for the high-compression mode");
+            }
+            ("lz4".to_string(), comp::lz4(0), decomp::lz4())
+        }
+        "lz4-hc" => {
+            let level: u32 = parse_level(level, 9)?;
+            if !(3..=12).contains(&level) {
+                anyhow::bail!("'lz4-hc' levels go from 3 to 12");
+            }
+            (format!("lz4-hc-{}", level), comp::lz4(level), decomp::lz4())
+        }
+        "snappy" => {
+            if level.is_some() {
+                anyhow::bail!("'snappy' has no level");
+            }
+            ("snappy".to_string(), comp::snappy(), decomp::snappy())
+        }
+        "xz" => {
+            let preset: u32 = parse_level(level, 6)?;
+            if preset > 9 {
+                anyhow::bail!("'xz' presets go from 0 to 9");
+            }
+            let extreme: bool = options.parse("extreme", false)?;
+            let title = format!("xz-{}{}", preset, if extreme { "e" } else { "" });
+            match options.take("mt") {
+                None | Some("off") => (title, comp::xz(preset, extreme), decomp::xz()),
+                Some(workers) => {
+                    let num_workers = parse_workers(workers)?;
+                    (
+                        format!("par-{}", title),
+                        comp::par_xz(preset, extreme, num_workers),
+                        decomp::xz(),
+                    )
+                }
+            }
+        }
+        "bzip2" => {
+            let level: u32 = parse_level(level, 9)?;
+            if !(1..=9).contains(&level) {
+                anyhow::bail!("'bzip2' levels go from 1 to 9");
+            }
+            (format!("bzip2-{}", level), comp::bzip2(level), decomp::bzip2())
+        }
+        _ => anyhow::bail!(
+            "unknown codec '{}', expected one of gzip, par-gzip, brotli, zstd, zstd-dict, zstd-opt, lz4, lz4-hc, snappy, xz, bzip2",
+            codec
+        ),
+    };
+    options.finish()?;
+    // zstd, lz4 and liblzma allocate with malloc, the other codecs through the rust allocator
+    let allocates_in_c = matches!(
+        codec,
+        "zstd" | "zstd-dict" | "zstd-opt" | "lz4" | "lz4-hc" | "xz"
+    );
+
+    Ok(Bfunc::new(
+        name.map(str::to_string).unwrap_or(title),
+        compressor,
+        decompressor,
+    )
+    .trained_on(hashes)
+    .allocates_in_c(allocates_in_c))
+}
+
+fn parse_level<T>(level: Option<&str>, default: T) -> anyhow::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match level {
+        Some(level) => level
+            .parse()
+            .with_context(|| format!("invalid level '{}'", level)),
+        None => Ok(default),
+    }
+}
+
+/// `auto` uses every available core
+pub fn parse_workers(workers: &str) -> anyhow::Result<u32> {
+    if workers == "auto" {
+        Ok(std::thread::available_parallelism()?.get() as u32)
+    } else {
+        workers
+            .parse()
+            .with_context(|| format!("invalid number of workers '{}'", workers))
+    }
+}
+
+struct Options<'a>(Vec<(&'a str, &'a str)>);
+
+impl<'a> Options<'a> {
+    fn take(&mut self, key: &str) -> Option<&'a str> {
+        let idx = self.0.iter().position(|(k, _)| *k == key)?;
+        Some(self.0.remove(idx).1)
+    }
+
+    fn parse<T>(&mut self, key: &str, default: T) -> anyhow::Result<T>
+    where
+        T: std::str::FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        match self.take(key) {
+            Some(value) => value
+                .parse()
+                .with_context(|| format!("invalid value '{}' for '{}'", value, key)),
+            None => Ok(default),
+        }
+    }
+
+    // errors on options that no codec consumed
+    fn finish(self) -> anyhow::Result<()> {
+        match self.0.first() {
+            Some((key, _)) => anyhow::bail!("unknown option '{}'", key),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Number of resamples used for the bootstrap confidence interval
+const BOOTSTRAP_RESAMPLES: usize = 1000;
+/// Confidence level of the bootstrap confidence interval
+const CONFIDENCE: f64 = 0.95;
+
+/// Summary of a timing series, in seconds
+#[derive(Serialize, Deserialize)]
+pub struct Summary {
+    pub num_of_samples: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+    /// median absolute deviation
+    pub mad: f64,
+    pub p5: f64,
+    pub p95: f64,
+    /// bootstrap confidence interval of the median
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+impl Summary {
+    pub fn new(durations: &[Duration]) -> Self {
+        let mut samples: Vec<f64> = durations.iter().map(Duration::as_secs_f64).collect();
+        samples.sort_by(f64::total_cmp);
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        // sample standard deviation, zero for a single sample
+        let std_dev = if samples.len() > 1 {
+            (samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let center = median(&samples);
+        let mut deviations: Vec<f64> = samples.iter().map(|x| (x - center).abs()).collect();
+        deviations.sort_by(f64::total_cmp);
+        let (ci_low, ci_high) = bootstrap_median_ci(&samples);
+
+        Self {
+            num_of_samples: samples.len(),
+            min: samples.first().copied().unwrap_or(f64::NAN),
+            max: samples.last().copied().unwrap_or(f64::NAN),
+            mean,
+            median: center,
+            std_dev,
+            mad: median(&deviations),
+            p5: percentile(&samples, 5.0),
+            p95: percentile(&samples, 95.0),
+            ci_low,
+            ci_high,
+        }
+    }
+
+    /// width of the confidence interval relative to the median
+    pub fn relative_ci(&self) -> f64 {
+        (self.ci_high - self.ci_low) / self.median
+    }
+}
+
+/// `sorted` must be sorted
+pub fn median(sorted: &[f64]) -> f64 {
+    let n = sorted.len();
+    if n == 0 {
+        f64::NAN
+    }
+    // if n is odd
+    else if n % 2 == 1 {
+        sorted[n / 2]
+    }
+    // if n is even
+    else {
+        (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+    }
+}
+
+/// Percentile with linear interpolation between the closest ranks, `sorted` must be sorted
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    match sorted.len() {
+        0 => f64::NAN,
+        1 => sorted[0],
+        n => {
+            let rank = p / 100.0 * (n - 1) as f64;
+            let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+            sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+        }
+    }
+}
+
+/// Percentile bootstrap confidence interval of the median
+fn bootstrap_median_ci(sorted: &[f64]) -> (f64, f64) {
+    if sorted.len() < 2 {
+        let value = sorted.first().copied().unwrap_or(f64::NAN);
+        return (value, value);
+    }
+
+    // fixed seed so that reports are reproducible
+    let mut rng = SplitMix64(0x5eed);
+    let mut resample: Vec<f64> = vec![0.0; sorted.len()];
+    let mut medians: Vec<f64> = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| {
+            for x in resample.iter_mut() {
+                *x = sorted[rng.next_below(sorted.len())];
+            }
+            resample.sort_by(f64::total_cmp);
+            median(&resample)
+        })
+        .collect();
+    medians.sort_by(f64::total_cmp);
+
+    let alpha = (1.0 - CONFIDENCE) / 2.0 * 100.0;
+    (
+        percentile(&medians, alpha),
+        percentile(&medians, 100.0 - alpha),
+    )
+}
+
+// small and good enough for resampling, avoids pulling in a rng crate
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_odd_length() {
+        assert_eq!(median(&[1.0, 2.0, 10.0]), 2.0);
+    }
+
+    #[test]
+    fn median_of_even_length() {
+        assert_eq!(median(&[1.0, 2.0, 4.0, 10.0]), 3.0);
+    }
+
+    #[test]
+    fn median_of_nothing() {
+        assert!(median(&[]).is_nan());
+    }
+
+    #[test]
+    fn percentile_interpolates() {
+        let sorted = [0.0, 10.0, 20.0, 30.0, 40.0];
+        assert_eq!(percentile(&sorted, 0.0), 0.0);
+        assert_eq!(percentile(&sorted, 50.0), 20.0);
+        assert_eq!(percentile(&sorted, 100.0), 40.0);
+        assert_eq!(percentile(&sorted, 95.0), 38.0);
+        assert_eq!(percentile(&[7.0], 5.0), 7.0);
+    }
+
+    #[test]
+    fn summary_of_single_sample() {
+        let summary = Summary::new(&[Duration::from_millis(500)]);
+        assert_eq!(summary.num_of_samples, 1);
+        assert_eq!(summary.median, 0.5);
+        assert_eq!(summary.mean, 0.5);
+        assert_eq!(summary.std_dev, 0.0);
+        assert_eq!(summary.mad, 0.0);
+        assert_eq!((summary.ci_low, summary.ci_high), (0.5, 0.5));
+    }
+
+    #[test]
+    fn summary_sorts_the_samples() {
+        let summary = Summary::new(&[4, 1, 3, 2].map(Duration::from_secs));
+        assert_eq!(summary.median, 2.5);
+        assert_eq!((summary.min, summary.max), (1.0, 4.0));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{bfunc::BenchResult, report::CodecReport};
+
+/// Notes are compared by decompressed size, small notes are the ones a dictionary could help,
+/// upper bounds in bytes
+pub const BUCKETS: [(&str, usize); 4] = [
+    ("< 16 KiB", 16 << 10),
+    ("16 KiB - 256 KiB", 256 << 10),
+    ("256 KiB - 4 MiB", 4 << 20),
+    ("> 4 MiB", usize::MAX),
+];
+
+/// Gains of a dictionary over zstd without one on the notes of a bucket,
+/// relative to zstd without a dictionary (0.1 is 10% better)
+pub struct Gain {
+    pub dict_size: usize,
+    pub bucket: &'static str,
+    pub num_of_notes: usize,
+    pub ratio: f64,
+    pub comp_throughput: f64,
+    pub decomp_throughput: f64,
+}
+
+pub struct Sweep {
+    pub gains: Vec<Gain>,
+}
+
+impl Sweep {
+    /// `dicts` are the results of every dictionary size, matched to the baseline by input name,
+    /// each bucket is summed up over its notes
+    pub fn new(baseline: &CodecReport, dicts: &[(usize, &CodecReport)]) -> Self {
+        let mut gains = Vec::new();
+        for (dict_size, dict) in dicts {
+            let mut lower = 0;
+            for (bucket, upper) in BUCKETS {
+                let pairs: Vec<_> = baseline
+                    .results
+                    .iter()
+                    .filter(|r| (lower..upper).contains(&r.uncompressed_size))
+                    .filter_map(|r| {
+                        dict.results
+                            .iter()
+                            .find(|d| d.input_name == r.input_name)
+                            .map(|d| (r, d))
+                    })
+                    .collect();
+                lower = upper;
+                if pairs.is_empty() {
+                    continue;
+                }
+                // the sums of the baseline over the sums with the dictionary
+                let sum = |f: fn(&(&BenchResult, &BenchResult)) -> (f64, f64)| {
+                    let (base, dict) = pairs
+                        .iter()
+                        .map(f)
+                        .fold((0.0, 0.0), |acc, (b, d)| (acc.0 + b, acc.1 + d));
+                    base / dict - 1.0
+                };
+                gains.push(Gain {
+                    dict_size: *dict_size,
+                    bucket,
+                    num_of_notes: pairs.len(),
+                    ratio: sum(|(b, d)| (b.compressed_size as f64, d.compressed_size as f64)),
+                    comp_throughput: sum(|(b, d)| (b.comp_time.median, d.comp_time.median)),
+                    decomp_throughput: sum(|(b, d)| (b.decomp_time.median, d.decomp_time.median)),
+                });
+            }
+        }
+        Self { gains }
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{:>10} {:<18} {:>6} {:>10} {:>14} {:>14}",
+            "dict size", "notes", "count", "ratio", "comp speed", "decomp speed"
+        );
+        for gain in self.gains.iter() {
+            println!(
+                "{:>10} {:<18} {:>6} {:>+9.1}% {:>+13.1}% {:>+13.1}%",
+                format_size(gain.dict_size),
+                gain.bucket,
+                gain.num_of_notes,
+                gain.ratio * 100.0,
+                gain.comp_throughput * 100.0,
+                gain.decomp_throughput * 100.0,
+            );
+        }
+    }
+}
+
+/// e.g. `64 KiB` or `2 MiB`
+pub fn format_size(size: usize) -> String {
+    if size >= 1 << 20 && size.is_multiple_of(1 << 20) {
+        format!("{} MiB", size >> 20)
+    } else if size.is_multiple_of(1 << 10) {
+        format!("{} KiB", size >> 10)
+    } else {
+        format!("{} B", size)
+    }
+}
@@ -1,32 +1,326 @@
-use std::{io::Read, path::Path};
-
-pub fn train(max_size: Option<usize>, path: &Path) -> Vec<u8> {
-    // 2 MiB default, medium size dict
-    let max_size = max_size.unwrap_or(2097152);
-
-    let (data, sizes) = path
-        .read_dir()
-        .unwrap()
-        .map(|res| {
-            let path = res.unwrap().path();
-            println!("{}", path.display());
-            let mut out: Vec<u8> = Vec::new();
-            std::fs::OpenOptions::new()
-                .read(true)
-                .open(path)
-                .unwrap()
-                .read_to_end(&mut out)
-                .unwrap();
-            crate::utils::decompress_default(&out)
-        })
-        .fold(
-            (Vec::<u8>::new(), Vec::<usize>::new()),
-            |(mut data, mut sizes), new_data| {
-                sizes.push(new_data.len());
-                data.extend(new_data);
-                (data, sizes)
-            },
+use std::{
+    ffi::CStr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use zstd::zstd_safe::zstd_sys;
+
+#[derive(Clone, Copy, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Algorithm {
+    Cover,
+    /// faster than cover on large training sets, the zstd default
+    FastCover,
+}
+
+/// Everything that decides which dictionary is trained, recorded in the sidecar
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Params {
+    /// maximum size of the dictionary in bytes
+    pub dict_size: usize,
+    /// only files with this extension are read
+    pub extension: String,
+    /// notes are split into samples of at most this many bytes
+    pub sample_size: usize,
+    /// samples are dropped evenly across the notes above this total
+    pub max_samples_size: usize,
+    pub algorithm: Algorithm,
+    /// segment size, 0 tries values between 50 and 2000
+    pub k: u32,
+    /// dmer size, 0 tries 6 and 8
+    pub d: u32,
+    /// number of (k, d) combinations to try, 0 is the zstd default
+    pub steps: u32,
+    /// log2 of the fastCOVER frequency table size, 0 is the zstd default
+    pub f: u32,
+    /// fastCOVER acceleration, 0 is the zstd default
+    pub accel: u32,
+    /// the compression level the dictionary statistics are tuned for
+    pub level: i32,
+    /// 0 derives the ID from a hash of the dictionary content
+    pub dict_id: u32,
+}
+
+/// The samples cut out of the training notes
+pub struct Samples {
+    data: Vec<u8>,
+    sizes: Vec<usize>,
+    inputs: Vec<TrainingInput>,
+}
+
+impl Samples {
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.data.len()
+    }
+}
+
+/// Describes a dictionary and the notes it was trained on, written next to the dictionary
+#[derive(Serialize, Deserialize)]
+pub struct TrainingSet {
+    pub dict_id: u32,
+    pub zstd_version: String,
+    /// the parameters after training, k and d are the ones picked by zstd when they were 0
+    pub params: Params,
+    pub num_of_samples: usize,
+    pub samples_size: usize,
+    pub inputs: Vec<TrainingInput>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TrainingInput {
+    pub name: String,
+    /// CRC32 of the decompressed note, the same hash as in the benchmark results
+    pub crc32: String,
+    /// number of samples of the note that were kept
+    pub samples: usize,
+}
+
+impl TrainingSet {
+    pub fn hashes(&self) -> Vec<u32> {
+        self.inputs
+            .iter()
+            .filter_map(|input| u32::from_str_radix(&input.crc32, 16).ok())
+            .collect()
+    }
+}
+
+/// Reads the notes of a directory (non-recursively) in name order and splits them into samples,
+/// files that are not gzip compressed are skipped with a warning
+pub fn collect(dir: &Path, params: &Params) -> anyhow::Result<Samples> {
+    let mut paths = crate::corpus::from_dir(dir, &params.extension)?;
+    // the directory order is not stable, the samples must be for reproducible dictionaries
+    paths.sort();
+
+    let mut notes: Vec<(String, Vec<u8>)> = Vec::new();
+    for path in paths {
+        let compressed =
+            std::fs::read(&path).with_context(|| format!("failed to read '{}'", path.display()))?;
+        let data = match crate::utils::decompress_default(&compressed) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("skipping '{}': {}", path.display(), e);
+                continue;
+            }
+        };
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        notes.push((name, data));
+    }
+    if notes.is_empty() {
+        anyhow::bail!(
+            "no .{} files found in '{}'",
+            params.extension,
+            dir.display()
         );
+    }
+    Ok(split(&notes, params.sample_size, params.max_samples_size))
+}
+
+/// Cuts the notes into samples of `sample_size` bytes and keeps at most `max_samples_size` bytes of them,
+/// spread over the notes by their share of the bytes
+fn split(notes: &[(String, Vec<u8>)], sample_size: usize, max_samples_size: usize) -> Samples {
+    let sample_size = sample_size.max(1);
+    let total: usize = notes.iter().map(|(_, data)| data.len()).sum();
+    // share of the bytes that is kept
+    let keep = (max_samples_size as f64 / total as f64).min(1.0);
+
+    let mut samples = Samples {
+        data: Vec::new(),
+        sizes: Vec::new(),
+        inputs: Vec::new(),
+    };
+    let mut seen = 0usize;
+    for (name, data) in notes.iter() {
+        let mut kept = 0;
+        for chunk in data.chunks(sample_size) {
+            seen += chunk.len();
+            // keeps a sample whenever the kept bytes fall behind their share of the bytes seen so far
+            if (samples.data.len() as f64) < seen as f64 * keep
+                && samples.data.len() + chunk.len() <= max_samples_size
+            {
+                samples.data.extend_from_slice(chunk);
+                samples.sizes.push(chunk.len());
+                kept += 1;
+            }
+        }
+        samples.inputs.push(TrainingInput {
+            name: name.clone(),
+            crc32: format!("{:08x}", crc32fast::hash(data)),
+            samples: kept,
+        });
+    }
+    samples
+}
+
+pub fn train(samples: &Samples, params: &Params) -> anyhow::Result<(Vec<u8>, TrainingSet)> {
+    let mut params = params.clone();
+    let mut dict: Vec<u8> = vec![0; params.dict_size];
+    let z_params = zstd_sys::ZDICT_params_t {
+        compressionLevel: params.level,
+        notificationLevel: 0,
+        dictID: params.dict_id,
+    };
+    let num_of_threads = std::thread::available_parallelism()?.get() as u32;
+
+    // SAFETY: the buffers outlive the calls and their lengths are passed along
+    let size = unsafe {
+        match params.algorithm {
+            Algorithm::Cover => {
+                let mut cover = zstd_sys::ZDICT_cover_params_t {
+                    k: params.k,
+                    d: params.d,
+                    steps: params.steps,
+                    nbThreads: num_of_threads,
+                    splitPoint: 0.0,
+                    shrinkDict: 0,
+                    shrinkDictMaxRegression: 0,
+                    zParams: z_params,
+                };
+                let size = zstd_sys::ZDICT_optimizeTrainFromBuffer_cover(
+                    dict.as_mut_ptr().cast(),
+                    dict.len(),
+                    samples.data.as_ptr().cast(),
+                    samples.sizes.as_ptr(),
+                    samples.sizes.len() as u32,
+                    &mut cover,
+                );
+                (params.k, params.d) = (cover.k, cover.d);
+                size
+            }
+            Algorithm::FastCover => {
+                let mut fast_cover = zstd_sys::ZDICT_fastCover_params_t {
+                    k: params.k,
+                    d: params.d,
+                    f: params.f,
+                    steps: params.steps,
+                    nbThreads: num_of_threads,
+                    splitPoint: 0.0,
+                    accel: params.accel,
+                    shrinkDict: 0,
+                    shrinkDictMaxRegression: 0,
+                    zParams: z_params,
+                };
+                let size = zstd_sys::ZDICT_optimizeTrainFromBuffer_fastCover(
+                    dict.as_mut_ptr().cast(),
+                    dict.len(),
+                    samples.data.as_ptr().cast(),
+                    samples.sizes.as_ptr(),
+                    samples.sizes.len() as u32,
+                    &mut fast_cover,
+                );
+                (params.k, params.d, params.f, params.accel) =
+                    (fast_cover.k, fast_cover.d, fast_cover.f, fast_cover.accel);
+                size
+            }
+        }
+    };
+    if unsafe { zstd_sys::ZDICT_isError(size) } != 0 {
+        let name = unsafe { CStr::from_ptr(zstd_sys::ZDICT_getErrorName(size)) };
+        anyhow::bail!(
+            "failed to train the dictionary on {} samples ({} bytes): {}, try smaller samples or more notes",
+            samples.len(),
+            samples.total_size(),
+            name.to_string_lossy()
+        );
+    }
+    dict.truncate(size);
+
+    let training_set = TrainingSet {
+        dict_id: zstd::zstd_safe::get_dict_id(&dict).map_or(0, |id| id.get()),
+        zstd_version: zstd_version(),
+        params,
+        num_of_samples: samples.len(),
+        samples_size: samples.total_size(),
+        inputs: samples.inputs.clone(),
+    };
+    Ok((dict, training_set))
+}
+
+/// e.g. `1.5.7`, dictionaries trained by other versions can differ
+fn zstd_version() -> String {
+    let version = zstd::zstd_safe::version_number();
+    format!(
+        "{}.{}.{}",
+        version / 10000,
+        version / 100 % 100,
+        version % 100
+    )
+}
+
+/// `rnote.dict` is described by `rnote.dict.json`
+pub fn sidecar_path(dict: &Path) -> PathBuf {
+    let mut path = dict.as_os_str().to_owned();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+pub fn save(path: &Path, dict: &[u8], training_set: &TrainingSet) -> anyhow::Result<()> {
+    std::fs::write(path, dict).with_context(|| format!("failed to write '{}'", path.display()))?;
+    let sidecar = sidecar_path(path);
+    let file = std::fs::File::create(&sidecar)
+        .with_context(|| format!("failed to create '{}'", sidecar.display()))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), training_set)?;
+    Ok(())
+}
+
+/// Reads a dictionary and its training set, if the sidecar exists
+pub fn load(path: &Path) -> anyhow::Result<(Vec<u8>, Option<TrainingSet>)> {
+    let dict =
+        std::fs::read(path).with_context(|| format!("failed to read '{}'", path.display()))?;
+    let sidecar = sidecar_path(path);
+    if !sidecar.exists() {
+        return Ok((dict, None));
+    }
+    let content = std::fs::read_to_string(&sidecar)
+        .with_context(|| format!("failed to read '{}'", sidecar.display()))?;
+    let training_set = serde_json::from_str(&content)
+        .with_context(|| format!("failed to parse '{}'", sidecar.display()))?;
+    Ok((dict, Some(training_set)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(sizes: &[usize]) -> Vec<(String, Vec<u8>)> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(i, size)| (format!("{}.rnote", i), vec![i as u8; *size]))
+            .collect()
+    }
+
+    #[test]
+    fn split_respects_the_cap() {
+        let notes = notes(&[1, 16 << 10]);
+        for max_samples_size in [9000, 8192] {
+            let samples = split(&notes, 16 << 10, max_samples_size);
+            assert!(samples.total_size() <= max_samples_size);
+        }
+    }
+
+    #[test]
+    fn split_spreads_over_the_notes() {
+        let notes = notes(&[64 << 10, 64 << 10]);
+        let samples = split(&notes, 1 << 10, 64 << 10);
+        assert_eq!(samples.total_size(), 64 << 10);
+        assert_eq!(samples.inputs[0].samples, 32);
+        assert_eq!(samples.inputs[1].samples, 32);
+    }
 
-    zstd::dict::from_continuous(&data, &sizes, max_size).unwrap()
+    #[test]
+    fn split_keeps_everything_below_the_cap() {
+        let notes = notes(&[1000, 3000]);
+        let samples = split(&notes, 1024, 1 << 20);
+        assert_eq!(samples.total_size(), 4000);
+        assert_eq!(samples.sizes, [1000, 1024, 1024, 952]);
+    }
 }
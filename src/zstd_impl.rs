@@ -24,6 +24,7 @@ pub fn decompress_from_zstd(compressed: &[u8]) -> Result<Vec<u8>, anyhow::Error>
         let fcs_sidx = (6 + did_field_size - single_segment_flag) as usize;
         // magic number: 4 bytes + window descriptor: 1 byte if single segment flag is not set + frame header descriptor: 1 byte + dict. field size: 0-4 bytes
         // testing suggests that dicts. don't improve the compression ratio and worsen writing/reading speeds, therefore they won't be used
+        // (reproduce with the `zstd-dict` codec, see the README)
         // thus this part could be simplified, but wouldn't strictly adhere to zstd standards
 
         match frame_content_size_flag {
@@ -83,28 +83,19 @@ pub fn compress_to_zstd(to_compress: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
     Ok(encoder.finish()?)
 }
 
-pub fn test() {
-    let decomp_4 = crate::utils::decompress_default(crate::COMP_4);
-    println!(
-        "Size = {}, L.E. = {:0>2x?}",
-        decomp_4.len(),
-        decomp_4.len().to_le_bytes()
-    );
-    let zstd_comp = compress_to_zstd(&decomp_4).unwrap();
-    decompress_from_zstd(&zstd_comp).unwrap();
+pub fn test(inputs: &[crate::corpus::Input]) {
+    for input in inputs {
+        println!(
+            "{}: Size = {}, L.E. = {:0>2x?}",
+            input.name,
+            input.data.len(),
+            input.data.len().to_le_bytes()
+        );
+        let zstd_comp = compress_to_zstd(&input.data).unwrap();
+        decompress_from_zstd(&zstd_comp).unwrap();
 
-    println!("\n\n");
-
-    let decomp_10 = crate::utils::decompress_default(crate::COMP_10);
-    println!(
-        "Size = {}, L.E. = {:0>2x?}",
-        decomp_10.len(),
-        decomp_10.len().to_le_bytes()
-    );
-    let zstd_comp = compress_to_zstd(&decomp_10).unwrap();
-    decompress_from_zstd(&zstd_comp).unwrap();
-
-    println!("\n\n");
+        println!("\n\n");
+    }
 
     let decomp_s = vec![0_u8; 233];
     println!(